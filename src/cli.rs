@@ -0,0 +1,473 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, BufRead, IsTerminal},
+    path::PathBuf,
+};
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+use crate::{
+    config::{self, Program},
+    launch,
+    settings::Settings,
+    vars,
+};
+
+#[derive(Parser)]
+#[command(version, about = "A TUI launcher for programs and games")]
+pub struct Cli {
+    /// Keep the launcher open after starting a program.
+    #[arg(long)]
+    pub stay_open: bool,
+    /// Run a program handed over on stdin and look after it, used by the launcher itself.
+    #[arg(long, hide = true)]
+    pub supervise: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print every entry as `id<TAB>title`.
+    List {
+        /// Print JSON instead.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Launch an entry.
+    Run {
+        /// Id or title of the entry.
+        entry: String,
+        /// Answer to one of the entry's params as NAME=VALUE, can be given more than
+        /// once. Params left out get their default.
+        #[arg(long = "param", value_parser = parse_env)]
+        params: Vec<(String, String)>,
+    },
+    /// Print everything about an entry.
+    Show {
+        /// Id or title of the entry.
+        entry: String,
+        /// Print JSON instead.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add an entry, written to a new file in the config directory.
+    Add {
+        #[arg(long)]
+        title: String,
+        /// Shell command to run.
+        #[arg(long, required_unless_present = "args", conflicts_with = "args")]
+        command: Option<String>,
+        /// Program and arguments to run without a shell, after `--`.
+        #[arg(last = true)]
+        args: Vec<String>,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Run it attached to the terminal.
+        #[arg(long)]
+        terminal: bool,
+        /// Tag to filter by, can be given more than once.
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Directory to start in.
+        #[arg(long)]
+        cwd: Option<PathBuf>,
+        /// Environment variable to set as NAME=VALUE, can be given more than once.
+        #[arg(long = "env", value_parser = parse_env)]
+        env: Vec<(String, String)>,
+        /// Folder in the config directory to put it in, e.g. `games`.
+        #[arg(long, default_value = "")]
+        category: String,
+        #[arg(long)]
+        id: Option<String>,
+    },
+    /// Delete an entry's file.
+    Remove {
+        /// Id or title of the entry.
+        entry: String,
+    },
+    /// Print a line per entry for dmenu, or with --launch start the line picked in it:
+    /// `glauncher dmenu | dmenu | glauncher dmenu --launch`
+    Dmenu {
+        /// Launch the entry on the line given, or read from stdin.
+        #[arg(long)]
+        launch: bool,
+        line: Option<String>,
+    },
+    /// Rofi script mode: `rofi -show glauncher -modi "glauncher:glauncher rofi"`
+    Rofi {
+        /// The line picked, passed in by rofi.
+        selection: Option<String>,
+    },
+}
+
+/// An entry as printed by `list --json` and `show --json`.
+#[derive(Serialize)]
+struct Entry<'a> {
+    id: String,
+    title: &'a str,
+    description: &'a str,
+    command: &'a str,
+    args: &'a [String],
+    terminal: bool,
+    tags: &'a [String],
+    cwd: Option<PathBuf>,
+    env: &'a BTreeMap<String, String>,
+    env_remove: &'a [String],
+    clear_env: bool,
+    params: &'a [config::Param],
+    pre_launch: &'a Option<Vec<String>>,
+    post_exit: &'a Option<Vec<String>>,
+    category: &'a str,
+    file: &'a PathBuf,
+}
+
+impl<'a> Entry<'a> {
+    fn new(program: &'a Program) -> Entry<'a> {
+        Entry {
+            id: program.id(),
+            title: &program.title,
+            description: &program.description,
+            command: &program.command,
+            args: &program.args,
+            terminal: program.terminal,
+            tags: &program.tags,
+            cwd: program.working_dir(),
+            env: &program.env,
+            env_remove: &program.env_remove,
+            clear_env: program.clear_env,
+            params: &program.params,
+            pre_launch: &program.pre_launch,
+            post_exit: &program.post_exit,
+            category: &program.category,
+            file: &program.source,
+        }
+    }
+}
+
+/// Runs a subcommand and returns the exit code.
+pub fn run(command: Command) -> i32 {
+    // Rust ignores SIGPIPE, so `glauncher list | head` would panic on the closed pipe.
+    // Go back to quietly exiting like any other command line tool.
+    // SAFETY: nothing else is running yet that could be handling signals.
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    match run_command(command) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("glauncher: {}", message);
+            1
+        }
+    }
+}
+
+fn run_command(command: Command) -> Result<i32, String> {
+    let config::Loaded {
+        path: config_path,
+        settings,
+        programs: list,
+        errors,
+    } = config::load().map_err(|e| e.to_string())?;
+    for error in &errors {
+        eprintln!("glauncher: warning: {}", error);
+    }
+
+    match command {
+        Command::List { json } => {
+            if json {
+                let entries: Vec<Entry> = list.iter().map(Entry::new).collect();
+                println!("{}", to_json(&entries)?);
+            } else {
+                for program in &list {
+                    println!("{}\t{}", program.id(), program.title);
+                }
+            }
+        }
+        Command::Run { entry, params } => {
+            return start(
+                find(&list, &entry)?,
+                &settings,
+                params.into_iter().collect(),
+                has_terminal(),
+            )
+        }
+        Command::Show { entry, json } => {
+            let program = find(&list, &entry)?;
+            if json {
+                println!("{}", to_json(&Entry::new(program))?);
+            } else {
+                println!("Title:    {}", program.title);
+                println!("Id:       {}", program.id());
+                println!("File:     {}", program.source.display());
+                println!("Command:  {}", program.command_line());
+                println!("Terminal: {}", if program.terminal { "yes" } else { "no" });
+                if !program.tags.is_empty() {
+                    println!("Tags:     {}", program.tags.join(", "));
+                }
+                if let Some(cwd) = program.working_dir() {
+                    println!("Dir:      {}", cwd.display());
+                }
+                if program.clear_env {
+                    println!("Env:      cleared");
+                }
+                for name in &program.env_remove {
+                    println!("Env:      -{}", name);
+                }
+                for (name, value) in &program.env {
+                    println!("Env:      {}={}", name, value);
+                }
+                for hook in program.pre_launch.as_ref().unwrap_or(&settings.pre_launch) {
+                    println!("Before:   {}", hook);
+                }
+                for hook in program.post_exit.as_ref().unwrap_or(&settings.post_exit) {
+                    println!("After:    {}", hook);
+                }
+                for param in &program.params {
+                    match &param.default {
+                        Some(default) => println!("Param:    {} (default {})", param.name, default),
+                        None => println!("Param:    {}", param.name),
+                    }
+                }
+                if !program.description.is_empty() {
+                    println!("\n{}", program.description.trim_end());
+                }
+            }
+        }
+        Command::Add {
+            title,
+            command,
+            args,
+            description,
+            terminal,
+            tags,
+            cwd,
+            env,
+            category,
+            id,
+        } => {
+            let dir = config::category_dir(&config_path, &category)?;
+            let program = Program {
+                id,
+                source: config::new_path(&dir, &title),
+                title,
+                description,
+                command: command.unwrap_or_default(),
+                args,
+                terminal,
+                tags,
+                cwd,
+                env: env.into_iter().collect(),
+                category,
+                ..Default::default()
+            };
+            config::validate(&program)?;
+            if let Some(existing) = list.iter().find(|p| p.id() == program.id()) {
+                return Err(format!(
+                    "An entry with the id {} already exists in {}",
+                    program.id(),
+                    existing.source.display()
+                ));
+            }
+            config::save(&program, &program.source)
+                .map_err(|e| format!("Could not save {}. {}", program.source.display(), e))?;
+            println!("{}", program.source.display());
+        }
+        Command::Remove { entry } => {
+            let program = find(&list, &entry)?;
+            if program.index.is_some() {
+                return Err(format!(
+                    "{} is one of several programs in {}, edit that file by hand",
+                    program.title,
+                    program.source.display()
+                ));
+            }
+            fs::remove_file(&program.source)
+                .map_err(|e| format!("Could not delete {}. {}", program.source.display(), e))?;
+        }
+        Command::Dmenu { launch, line } => {
+            if !launch {
+                for program in list.iter().filter(|p| can_open(p)) {
+                    println!("{}", menu_line(&list, program));
+                }
+                return Ok(0);
+            }
+            let line = match line {
+                Some(line) => line,
+                None => {
+                    let mut line = String::new();
+                    io::stdin()
+                        .lock()
+                        .read_line(&mut line)
+                        .map_err(|e| e.to_string())?;
+                    line
+                }
+            };
+            let line = line.trim_end_matches('\n');
+            // Nothing picked, dmenu was cancelled.
+            if line.is_empty() {
+                return Ok(1);
+            }
+            let program = list
+                .iter()
+                .find(|program| menu_line(&list, program) == line)
+                .map_or_else(|| find(&list, line), Ok)?;
+            return start(program, &settings, BTreeMap::new(), has_terminal());
+        }
+        Command::Rofi { selection } => return rofi(&list, &settings, selection),
+    }
+    Ok(0)
+}
+
+/// Launches `program` with `answers` to its params. A terminal program runs in the
+/// foreground if we were started from a terminal, `attached`, and otherwise in a new
+/// `$TERMINAL` window.
+fn start(
+    program: &Program,
+    settings: &Settings,
+    answers: BTreeMap<String, String>,
+    attached: bool,
+) -> Result<i32, String> {
+    let mut program = program.clone();
+    vars::fill_params(&mut program, &answers)
+        .map_err(|e| format!("Could not start {}. {}.", program.title, e))?;
+    let mut job = launch::Job::new(&program, settings);
+    if program.terminal && attached {
+        let (status, problems) = launch::run_foreground(&job);
+        for problem in problems {
+            eprintln!("glauncher: {}", problem);
+        }
+        let status = status.map_err(|e| format!("Could not start {}. {}", program.title, e))?;
+        return Ok(status.code().unwrap_or(1));
+    }
+    if program.terminal {
+        let Some(terminal) = terminal_emulator() else {
+            return Err(format!(
+                "{} has to run in a terminal, set $TERMINAL to open it in one",
+                program.title
+            ));
+        };
+        // Most terminal emulators take the program to run after `-e`.
+        job.argv = terminal
+            .into_iter()
+            .chain(["-e".to_string()])
+            .chain(job.argv)
+            .collect();
+        job.shell = false;
+    }
+    launch::spawn(&job).map_err(|e| format!("Could not start {}. {}", program.title, e))?;
+    Ok(0)
+}
+
+/// Whether we're running in a terminal that a terminal program can take over.
+fn has_terminal() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// `$TERMINAL` split into the program and its arguments, e.g. `kitty --single-instance`.
+fn terminal_emulator() -> Option<Vec<String>> {
+    let terminal = env::var("TERMINAL").ok()?;
+    let argv: Vec<String> = terminal.split_whitespace().map(str::to_string).collect();
+    (!argv.is_empty()).then_some(argv)
+}
+
+/// Whether a menu like dmenu or rofi, which has no terminal of its own, can launch
+/// `program`.
+fn can_open(program: &Program) -> bool {
+    !program.terminal || terminal_emulator().is_some()
+}
+
+/// How an entry is shown in dmenu and rofi. Just the title, unless another entry has
+/// the same one, then the id is added so the line can be told apart when it comes back.
+fn menu_line(list: &[Program], program: &Program) -> String {
+    let shared = list
+        .iter()
+        .filter(|other| other.title == program.title)
+        .count()
+        > 1;
+    if shared {
+        format!("{} ({})", program.title, program.id())
+    } else {
+        program.title.clone()
+    }
+}
+
+/// Rofi runs the script once with no arguments to get the rows, then again with the
+/// picked row. Which of those it is, is in `ROFI_RETV`: 0 for the first run, 1 for a
+/// row being picked and 2 for text typed that didn't match a row.
+/// See rofi-script(5).
+fn rofi(list: &[Program], settings: &Settings, selection: Option<String>) -> Result<i32, String> {
+    let retv = env::var("ROFI_RETV").unwrap_or_default();
+    let mut message = None;
+    match (retv.as_str(), selection) {
+        ("1", Some(selection)) => {
+            // The id we attached to the row, which survives duplicate titles.
+            let picked = match env::var("ROFI_INFO") {
+                Ok(id) if !id.is_empty() => find(list, &id),
+                _ => find(list, &selection),
+            };
+            return start(picked?, settings, BTreeMap::new(), false);
+        }
+        ("2", Some(typed)) => match find(list, &typed) {
+            Ok(program) => return start(program, settings, BTreeMap::new(), false),
+            Err(e) => message = Some(e),
+        },
+        _ => {}
+    }
+
+    println!("\0prompt\x1fGLauncher");
+    println!("\0no-custom\x1ffalse");
+    if let Some(message) = message {
+        println!("\0message\x1f{}", message);
+    }
+    for program in list.iter().filter(|p| can_open(p)) {
+        // `meta` is matched when filtering but not shown.
+        println!(
+            "{}\0info\x1f{}\x1fmeta\x1f{} {}",
+            menu_line(list, program),
+            program.id(),
+            program.category,
+            program.description.replace('\n', " ")
+        );
+    }
+    Ok(0)
+}
+
+/// Looks an entry up by id, then by title ignoring case. Anything after a tab is
+/// ignored, so lines from `glauncher list` can be passed straight back in.
+fn find<'a>(list: &'a [Program], entry: &str) -> Result<&'a Program, String> {
+    let entry = entry.split('\t').next().unwrap_or_default();
+    if let Some(program) = list.iter().find(|program| program.id() == entry) {
+        return Ok(program);
+    }
+    let matches: Vec<&Program> = list
+        .iter()
+        .filter(|program| program.title.eq_ignore_ascii_case(entry))
+        .collect();
+    match matches[..] {
+        [program] => Ok(program),
+        [] => Err(format!("No entry with the id or title {}", entry)),
+        _ => Err(format!(
+            "Several entries are titled {}, use one of their ids: {}",
+            entry,
+            matches
+                .iter()
+                .map(|program| program.id())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+fn parse_env(text: &str) -> Result<(String, String), String> {
+    match text.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got {}", text)),
+    }
+}
+
+fn to_json(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+}
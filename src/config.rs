@@ -0,0 +1,671 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    settings::{self, Settings},
+    theme,
+    vars::Vars,
+};
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Program {
+    /// Stable name used to keep logs and history. Defaults to the category and title,
+    /// set it to keep them across a rename.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Run by the shell, so pipes, `~` and `$VARS` work.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub command: String,
+    /// Run directly without a shell, the program first and then each argument as is.
+    /// Used instead of `command`, it saves quoting paths with spaces and quotes in them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Directory to start in. `~` is the home directory and relative paths are from the
+    /// folder the entry's file is in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Start with an empty environment, only `env` gets set.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub clear_env: bool,
+    /// Variables to leave out of the environment.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env_remove: Vec<String>,
+    /// Variables to set, e.g. `env = { DXVK_HUD = "fps" }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Shell commands run one after another before the entry starts. If one fails the
+    /// entry isn't started. Leave out for the `pre_launch` in the settings, `[]` for none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_launch: Option<Vec<String>>,
+    /// Shell commands run one after another once the entry exits, with its exit code in
+    /// `$GLAUNCHER_EXIT_CODE`. Leave out for the `post_exit` in the settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_exit: Option<Vec<String>>,
+    /// The entry as written, before `${...}` variables were filled in. `None` if it
+    /// didn't use any.
+    #[serde(skip)]
+    pub original: Option<Box<Program>>,
+    /// Run attached to the launcher's terminal, for programs like htop or ssh.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub terminal: bool,
+    /// Free form labels to filter the list by, for entries that fit several categories.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Values asked for each time the entry is launched, used as `${param.<name>}`.
+    #[serde(default, rename = "param", skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<Param>,
+    /// Path of the folder the entry was found in relative to the config directory,
+    /// e.g. `games/vr`. Empty for entries at the top level.
+    #[serde(skip)]
+    pub category: String,
+    /// File the entry was loaded from.
+    #[serde(skip)]
+    pub source: PathBuf,
+    /// Position in the file's `[[program]]` array, `None` if the file is just this entry.
+    #[serde(skip)]
+    pub index: Option<usize>,
+}
+
+/// Something to ask for before launching, written as `[[program.param]]`.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Param {
+    pub name: String,
+    /// Shown when asking, the name if it's not set.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prompt: String,
+    /// The answers to pick from. Anything can be typed in if there are none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+impl Param {
+    pub fn label(&self) -> &str {
+        if self.prompt.is_empty() {
+            &self.name
+        } else {
+            &self.prompt
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Program {
+    /// The entry's id, e.g. `games/steam-big-picture` for "Steam Big Picture" in `games/`.
+    pub fn id(&self) -> String {
+        if let Some(id) = &self.id {
+            return id.clone();
+        }
+        self.category
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .chain([self.title.as_str()])
+            .map(slug)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// The arguments to run, either `args` or `command` handed to `shell`.
+    pub fn argv(&self, shell: &str) -> Vec<String> {
+        if self.args.is_empty() {
+            vec![shell.to_string(), "-c".to_string(), self.command.clone()]
+        } else {
+            self.args.clone()
+        }
+    }
+
+    /// What's run, as it would be typed into a shell.
+    pub fn command_line(&self) -> String {
+        if self.args.is_empty() {
+            self.command.clone()
+        } else {
+            join_args(&self.args)
+        }
+    }
+
+    /// The directory to start in with `~` and relative paths worked out.
+    pub fn working_dir(&self) -> Option<PathBuf> {
+        let cwd = self.cwd.as_ref()?;
+        let cwd = match (cwd.strip_prefix("~"), dirs::home_dir()) {
+            (Ok(rest), Some(home)) => home.join(rest),
+            _ => cwd.clone(),
+        };
+        Some(match self.source.parent() {
+            Some(dir) => dir.join(cwd),
+            None => cwd,
+        })
+    }
+
+    /// Makes sure the entry can be run, see `check_id`, `check_command` and
+    /// `check_params`.
+    pub fn check(&self) -> Result<(), String> {
+        self.check_id()?;
+        self.check_command()?;
+        self.check_params()
+    }
+
+    /// Makes sure a set `id` is safe to use as a path under the logs directory: parts
+    /// split by `/` that aren't empty, `.` or `..`, and no spaces or control characters.
+    fn check_id(&self) -> Result<(), String> {
+        let Some(id) = &self.id else {
+            return Ok(());
+        };
+        let bad_part = id
+            .split('/')
+            .any(|part| part.is_empty() || part == "." || part == "..");
+        let bad_char = id
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '\\');
+        if bad_part || bad_char {
+            return Err(format!(
+                "The id `{}` can't be empty, contain spaces, or have empty, `.` or `..` parts.",
+                id.escape_debug()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Makes sure exactly one of `command` and `args` is set.
+    fn check_command(&self) -> Result<(), String> {
+        match (self.command.trim().is_empty(), self.args.is_empty()) {
+            (false, false) => Err("Set either `command` or `args`, not both.".to_string()),
+            (true, true) => Err("A `command` or `args` is needed.".to_string()),
+            _ if self.args.first().is_some_and(|program| program.is_empty()) => {
+                Err("The first of `args` has to be the program to run.".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Makes sure every param has a name of its own and a default that's one of its
+    /// choices.
+    fn check_params(&self) -> Result<(), String> {
+        for (i, param) in self.params.iter().enumerate() {
+            let valid = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+            if param.name.is_empty() || !param.name.chars().all(valid) {
+                return Err(format!(
+                    "param[{}] needs a `name` of letters, numbers, `_` and `-`.",
+                    i
+                ));
+            }
+            if self.params[..i].iter().any(|p| p.name == param.name) {
+                return Err(format!(
+                    "There's more than one param named `{}`.",
+                    param.name
+                ));
+            }
+            let stray = param
+                .default
+                .as_ref()
+                .filter(|default| !param.choices.is_empty() && !param.choices.contains(default));
+            if let Some(default) = stray {
+                return Err(format!(
+                    "The default `{}` for param `{}` isn't one of its choices.",
+                    default, param.name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Joins `args` with spaces, quoting those that need it for a shell.
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `arg` quoted so a shell reads it back as one word, as is.
+pub fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// A config file, or one entry in it, that couldn't be loaded.
+pub struct LoadError {
+    pub path: PathBuf,
+    /// Line and column of the problem, counting from 1, when we know it.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl LoadError {
+    pub fn new(path: &Path, message: impl ToString) -> LoadError {
+        LoadError {
+            path: path.to_path_buf(),
+            position: None,
+            message: message.to_string(),
+        }
+    }
+
+    /// An error at byte `offset` of `contents`.
+    fn at(path: &Path, contents: &str, offset: usize, message: impl ToString) -> LoadError {
+        let before = &contents[..offset.min(contents.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1;
+        LoadError {
+            position: Some((line, column)),
+            ..LoadError::new(path, message)
+        }
+    }
+
+    pub fn from_toml(path: &Path, contents: &str, error: &toml::de::Error) -> LoadError {
+        let message = error.message().trim_end();
+        match error.span() {
+            Some(span) => LoadError::at(path, contents, span.start, message),
+            None => LoadError::new(path, message),
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some((line, column)) = self.position {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// `~/.config/glauncher`, whether it exists or not.
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join("glauncher"))
+}
+
+/// `~/.config/glauncher`, made if it doesn't exist yet.
+pub fn dir() -> Result<PathBuf, LoadError> {
+    let Some(config_path) = path() else {
+        return Err(LoadError::new(
+            Path::new("~/.config"),
+            "Could not find the config directory, is $HOME set?",
+        ));
+    };
+    if !config_path.exists() {
+        if let Err(e) = fs::create_dir_all(&config_path) {
+            return Err(LoadError::new(
+                &config_path,
+                format!("Could not make config directory. {}", e),
+            ));
+        }
+    }
+    Ok(config_path)
+}
+
+/// Everything read from the config directory.
+pub struct Loaded {
+    pub path: PathBuf,
+    pub settings: Settings,
+    pub programs: Vec<Program>,
+    pub errors: Vec<LoadError>,
+}
+
+/// Finds the config directory and loads the settings and every program in it.
+pub fn load() -> Result<Loaded, LoadError> {
+    Ok(load_from(dir()?))
+}
+
+/// Loads the settings and every program in the config directory `path`.
+pub fn load_from(path: PathBuf) -> Loaded {
+    let (mut settings, mut errors) = settings::load(&path);
+    let vars = Vars::new(&path, &settings.vars);
+    if let Err(e) = vars.expand_settings(&mut settings) {
+        errors.push(LoadError::new(&path.join(settings::FILE), e));
+    }
+    let (programs, mut program_errors) = load_dir(&path, &vars);
+    errors.append(&mut program_errors);
+    Loaded {
+        path,
+        settings,
+        programs,
+        errors,
+    }
+}
+
+/// Recursively loads every `*.toml` file under `root`. Subdirectories become
+/// categories. Unreadable files and folders are reported rather than aborting the load.
+pub fn load_dir(root: &Path, vars: &Vars) -> (Vec<Program>, Vec<LoadError>) {
+    let mut programs = Vec::new();
+    let mut errors = Vec::new();
+    walk(root, root, vars, &mut programs, &mut errors);
+    errors.extend(drop_duplicates(&mut programs));
+    (programs, errors)
+}
+
+/// Keeps only the first entry with each id, as history, logs and pins would otherwise
+/// mix them up. The others are reported.
+pub fn drop_duplicates(programs: &mut Vec<Program>) -> Vec<LoadError> {
+    let mut seen: HashMap<String, PathBuf> = HashMap::new();
+    let mut errors = Vec::new();
+    programs.retain(|program| {
+        let id = program.id();
+        let Some(first) = seen.get(&id) else {
+            seen.insert(id, program.source.clone());
+            return true;
+        };
+        let message = format!(
+            "the id `{}` is already used by an entry in {}, set a different `id`",
+            id,
+            first.display()
+        );
+        let message = match program.index {
+            Some(i) => format!("program[{}]: {}", i, message),
+            None => message,
+        };
+        errors.push(LoadError::new(&program.source, message));
+        false
+    });
+    errors
+}
+
+fn walk(
+    root: &Path,
+    dir: &Path,
+    vars: &Vars,
+    programs: &mut Vec<Program>,
+    errors: &mut Vec<LoadError>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(LoadError::new(dir, e));
+            return;
+        }
+    };
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                errors.push(LoadError::new(dir, e));
+                continue;
+            }
+        };
+        if is_ignored(&path) || path == root.join(settings::FILE) || path == root.join(theme::DIR) {
+            continue;
+        }
+        if path.is_dir() {
+            dirs.push(path);
+        } else if is_config_file(&path) {
+            files.push(path);
+        }
+    }
+    // read_dir order differs between filesystems, sort so every machine agrees.
+    files.sort();
+    dirs.sort();
+
+    for path in files {
+        let (mut found, mut errs) = load_file(root, &path, vars);
+        programs.append(&mut found);
+        errors.append(&mut errs);
+    }
+    for path in dirs {
+        walk(root, &path, vars, programs, errors);
+    }
+}
+
+/// Loads the file at `path`, which is somewhere under the config directory `root`.
+pub fn load_file(root: &Path, path: &Path, vars: &Vars) -> (Vec<Program>, Vec<LoadError>) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return (Vec::new(), vec![LoadError::new(path, e)]),
+    };
+    let category = path
+        .parent()
+        .map(|dir| category_of(root, dir))
+        .unwrap_or_default();
+    let (programs, mut errors) = parse_file(path, &contents);
+    let mut expanded = Vec::new();
+    for mut program in programs {
+        program.category = category.clone();
+        program.source = path.to_path_buf();
+        match vars.expand(&mut program) {
+            Ok(()) => expanded.push(program),
+            Err(e) => {
+                let message = match program.index {
+                    Some(i) => format!("program[{}]: {}", i, e),
+                    None => e,
+                };
+                errors.push(LoadError::new(path, message));
+            }
+        }
+    }
+    (expanded, errors)
+}
+
+/// Whether `path` looks like a file we'd load, going by its name alone.
+pub fn is_config_file(path: &Path) -> bool {
+    !is_ignored(path) && path.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// Hidden files and the backup files editors leave lying around.
+fn is_ignored(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return true;
+    };
+    name.starts_with('.') || name.starts_with('#') || name.ends_with('~')
+}
+
+fn category_of(root: &Path, dir: &Path) -> String {
+    let relative = dir
+        .strip_prefix(root)
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Parses one config file. A file is either a single program table or holds any
+/// number of them in a `[[program]]` array. Entries that fail to parse are reported
+/// by file and array index without throwing away the rest of the file.
+pub fn parse_file(path: &Path, contents: &str) -> (Vec<Program>, Vec<LoadError>) {
+    let mut programs = Vec::new();
+    let mut errors = Vec::new();
+
+    let table = match toml::from_str::<toml::Table>(contents) {
+        Ok(table) => table,
+        Err(e) => {
+            errors.push(LoadError::from_toml(path, contents, &e));
+            return (programs, errors);
+        }
+    };
+
+    match table.get("program") {
+        Some(toml::Value::Array(_)) => {
+            // Parse again keeping the spans so errors can point at the broken entry.
+            #[derive(Deserialize)]
+            struct ProgramArray {
+                program: Vec<toml::Spanned<toml::Value>>,
+            }
+            let entries = match toml::from_str::<ProgramArray>(contents) {
+                Ok(file) => file.program,
+                Err(e) => {
+                    errors.push(LoadError::from_toml(path, contents, &e));
+                    return (programs, errors);
+                }
+            };
+            for (i, entry) in entries.into_iter().enumerate() {
+                let start = entry.span().start;
+                let parsed = Program::deserialize(entry.into_inner())
+                    .map_err(|e| e.message().trim_end().to_string())
+                    .and_then(|program| program.check().map(|()| program));
+                match parsed {
+                    Ok(mut program) => {
+                        program.index = Some(i);
+                        programs.push(program)
+                    }
+                    Err(e) => errors.push(LoadError::at(
+                        path,
+                        contents,
+                        start,
+                        format!("program[{}]: {}", i, e),
+                    )),
+                }
+            }
+        }
+        Some(_) => errors.push(LoadError::new(
+            path,
+            "`program` must be an array of tables, write it as [[program]]",
+        )),
+        None => match toml::from_str::<Program>(contents) {
+            Ok(program) => match program.check() {
+                Ok(()) => programs.push(program),
+                Err(e) => errors.push(LoadError::new(path, e)),
+            },
+            Err(e) => errors.push(LoadError::from_toml(path, contents, &e)),
+        },
+    }
+    (programs, errors)
+}
+
+/// Checks an entry made in the launcher or from the command line before it's saved.
+pub fn validate(program: &Program) -> Result<(), String> {
+    if program.title.trim().is_empty() {
+        Err("A title is needed.".to_string())
+    } else if let Err(e) = program.check() {
+        Err(e)
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes `program` out as a file of its own at `path`.
+pub fn save(program: &Program, path: &Path) -> io::Result<()> {
+    let contents = toml::to_string(program).map_err(io::Error::other)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+/// The folder in `root` for `category`, e.g. `games/vr`. It has to stay inside `root`,
+/// so absolute paths, `..` and symlinks that lead out of it are refused.
+pub fn category_dir(root: &Path, category: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(category);
+    let inside = relative
+        .components()
+        .all(|part| matches!(part, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(format!(
+            "The category {} has to be a folder inside {}",
+            category,
+            root.display()
+        ));
+    }
+    let dir = root.join(relative);
+    // The part of it that's there already, which is where a symlink could lead out.
+    let existing = dir.ancestors().find(|dir| dir.exists()).unwrap_or(root);
+    match (existing.canonicalize(), root.canonicalize()) {
+        (Ok(existing), Ok(root)) if !existing.starts_with(&root) => Err(format!(
+            "The category {} leads outside {}",
+            category,
+            root.display()
+        )),
+        _ => Ok(dir),
+    }
+}
+
+/// A path in `dir` for a new file named after `title` that doesn't clash with an
+/// existing one.
+pub fn new_path(dir: &Path, title: &str) -> PathBuf {
+    let name = match slug(title) {
+        name if name.is_empty() => "program".to_string(),
+        name => name,
+    };
+    let mut path = dir.join(format!("{}.toml", name));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.toml", name, n));
+        n += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Vec<Program> {
+        let (programs, errors) = parse_file(Path::new("test.toml"), contents);
+        let errors: Vec<String> = errors.iter().map(LoadError::to_string).collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+        programs
+    }
+
+    #[test]
+    fn description_is_optional() {
+        let programs = parse(
+            r#"
+title = "Baldur's Gate"
+args = ["/home/me/Games/Baldur's Gate/start.sh", "--fullscreen"]
+"#,
+        );
+        assert_eq!(programs[0].description, "");
+        assert_eq!(programs[0].args.len(), 2);
+    }
+
+    #[test]
+    fn params_are_read_from_program_arrays() {
+        let programs = parse(
+            r#"
+[[program]]
+title = "SSH"
+command = "ssh ${param.host}"
+terminal = true
+
+[[program.param]]
+name = "host"
+prompt = "SSH host"
+choices = ["nas", "pi", "build-box"]
+default = "nas"
+"#,
+        );
+        let param = &programs[0].params[0];
+        assert_eq!(param.label(), "SSH host");
+        assert_eq!(param.choices, ["nas", "pi", "build-box"]);
+        assert_eq!(param.default.as_deref(), Some("nas"));
+    }
+}
@@ -0,0 +1,215 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::theme::Theme;
+
+pub enum Kind {
+    /// A single line of text.
+    Line,
+    /// Text where Enter starts a new line.
+    Text,
+    /// A yes/no switch toggled with Space.
+    Toggle,
+    /// One of a list, cycled through with Left/Right or Space.
+    Choice(Vec<String>),
+}
+
+pub struct Field {
+    pub label: String,
+    pub value: String,
+    pub kind: Kind,
+    // Cursor position in chars.
+    cursor: usize,
+}
+
+impl Field {
+    pub fn new(label: impl ToString, value: impl ToString, kind: Kind) -> Field {
+        let value = value.to_string();
+        Field {
+            label: label.to_string(),
+            cursor: value.chars().count(),
+            value,
+            kind,
+        }
+    }
+
+    pub fn toggle(label: impl ToString, on: bool) -> Field {
+        Field::new(label, if on { "yes" } else { "no" }, Kind::Toggle)
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.value == "yes"
+    }
+
+    /// Moves a choice field `by` steps through its choices, wrapping around.
+    fn cycle(&mut self, by: isize) {
+        let Kind::Choice(choices) = &self.kind else {
+            return;
+        };
+        if choices.is_empty() {
+            return;
+        }
+        let at = choices.iter().position(|choice| *choice == self.value);
+        let next = match at {
+            Some(at) => (at as isize + by).rem_euclid(choices.len() as isize) as usize,
+            None => 0,
+        };
+        self.value = choices[next].clone();
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+
+    fn insert(&mut self, c: char) {
+        let i = self.byte_index(self.cursor);
+        self.value.insert(i, c);
+        self.cursor += 1;
+    }
+
+    fn height(&self) -> u16 {
+        match self.kind {
+            Kind::Text => 6,
+            _ => 3,
+        }
+    }
+}
+
+pub enum Outcome {
+    Continue,
+    Submit,
+    Cancel,
+}
+
+/// A stack of labelled input fields shown as a popup, used to edit entries and to ask
+/// for launch parameters.
+pub struct Form {
+    pub title: String,
+    pub fields: Vec<Field>,
+    pub focus: usize,
+    /// Validation problem shown under the fields.
+    pub error: Option<String>,
+    /// What submitting does, shown in the key hints.
+    pub submit: &'static str,
+}
+
+impl Form {
+    pub fn new(title: impl ToString, fields: Vec<Field>) -> Form {
+        Form {
+            title: title.to_string(),
+            fields,
+            focus: 0,
+            error: None,
+            submit: "save",
+        }
+    }
+
+    pub fn value(&self, label: &str) -> &str {
+        self.fields
+            .iter()
+            .find(|field| field.label == label)
+            .map_or("", |field| field.value.as_str())
+    }
+
+    pub fn field_on(&self, label: &str) -> bool {
+        self.value(label) == "yes"
+    }
+
+    /// Tab/Shift-Tab or Up/Down move between fields, Enter moves on (or adds a line to a
+    /// text field), Ctrl-S or Enter on the last field submits and Esc cancels.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Outcome {
+        let last = self.fields.len().saturating_sub(1);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let field = &mut self.fields[self.focus];
+        match key.code {
+            KeyCode::Esc => return Outcome::Cancel,
+            KeyCode::Char('s') if ctrl => return Outcome::Submit,
+            KeyCode::Tab | KeyCode::Down => self.focus = (self.focus + 1).min(last),
+            KeyCode::BackTab | KeyCode::Up => self.focus = self.focus.saturating_sub(1),
+            KeyCode::Enter if matches!(field.kind, Kind::Text) => field.insert('\n'),
+            KeyCode::Enter if self.focus == last => return Outcome::Submit,
+            KeyCode::Enter => self.focus += 1,
+            KeyCode::Char(' ') if matches!(field.kind, Kind::Toggle) => {
+                let on = !field.is_on();
+                *field = Field::toggle(field.label.clone(), on);
+            }
+            KeyCode::Left if matches!(field.kind, Kind::Choice(_)) => field.cycle(-1),
+            KeyCode::Right | KeyCode::Char(' ') if matches!(field.kind, Kind::Choice(_)) => {
+                field.cycle(1)
+            }
+            _ if matches!(field.kind, Kind::Toggle | Kind::Choice(_)) => {}
+            KeyCode::Left => field.cursor = field.cursor.saturating_sub(1),
+            KeyCode::Right => field.cursor = (field.cursor + 1).min(field.value.chars().count()),
+            KeyCode::Home => field.cursor = 0,
+            KeyCode::End => field.cursor = field.value.chars().count(),
+            KeyCode::Backspace if field.cursor > 0 => {
+                field.cursor -= 1;
+                let i = field.byte_index(field.cursor);
+                field.value.remove(i);
+            }
+            KeyCode::Delete if field.cursor < field.value.chars().count() => {
+                let i = field.byte_index(field.cursor);
+                field.value.remove(i);
+            }
+            KeyCode::Char(c) if !ctrl => field.insert(c),
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let height = self.fields.iter().map(Field::height).sum::<u16>() + 4;
+        let area = crate::centered_rect(70, height.min(area.height), area);
+        frame.render_widget(Clear, area);
+        let block = theme
+            .block()
+            .title(self.title.clone())
+            .title_bottom(format!("Tab next · Ctrl-S {} · Esc cancel", self.submit));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let mut constraints: Vec<Constraint> = self
+            .fields
+            .iter()
+            .map(|field| Constraint::Length(field.height()))
+            .collect();
+        constraints.push(Constraint::Min(0));
+        let layout = Layout::new(Direction::Vertical, constraints).split(inner);
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let focused = i == self.focus;
+            let mut text = field.value.clone();
+            if focused && matches!(field.kind, Kind::Line | Kind::Text) {
+                let at = field.byte_index(field.cursor);
+                text.insert(at, '▏');
+            }
+            let label = match &field.kind {
+                Kind::Toggle => format!("{} (Space)", field.label),
+                Kind::Choice(_) => format!("{} (Left/Right)", field.label),
+                _ => field.label.clone(),
+            };
+            let mut block = theme.block().title(label);
+            if focused {
+                block = block.border_style(theme.focus);
+            }
+            // Keep the cursor in view in multi line fields.
+            let lines = text[..text.find('▏').unwrap_or(text.len())]
+                .matches('\n')
+                .count() as u16;
+            let scroll = lines.saturating_sub(field.height().saturating_sub(3));
+            frame.render_widget(
+                Paragraph::new(text).block(block).scroll((scroll, 0)),
+                layout[i],
+            );
+        }
+        if let Some(error) = &self.error {
+            frame.render_widget(
+                Paragraph::new(error.clone()).style(theme.error),
+                layout[self.fields.len()],
+            );
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    os::fd::AsRawFd,
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use crate::{clock, logs};
+
+/// One launch of an entry. It's recorded when it starts and again once it exits.
+pub struct Run {
+    /// Unix time it was started at.
+    pub started: u64,
+    /// Seconds until it exited, `None` while it's running or if it was never seen to
+    /// exit, say because the machine was turned off.
+    pub duration: Option<u64>,
+    pub code: Option<i32>,
+    pub id: String,
+    /// Process id of the program, which tells apart runs of an entry started in the same
+    /// second. `None` for runs recorded before it was kept.
+    pub pid: Option<u32>,
+}
+
+impl Run {
+    fn line(&self) -> String {
+        let field = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            self.started,
+            field(self.duration.map(|duration| duration.to_string())),
+            field(self.code.map(|code| code.to_string())),
+            self.id,
+            field(self.pid.map(|pid| pid.to_string()))
+        )
+    }
+
+    fn parse(line: &str) -> Option<Run> {
+        // Ids can't hold whitespace, so older lines simply end after the id.
+        let mut fields = line.splitn(5, '\t');
+        let started = fields.next()?.parse().ok()?;
+        let duration = fields.next()?;
+        let code = fields.next()?;
+        Some(Run {
+            started,
+            duration: (duration != "-")
+                .then(|| duration.parse())
+                .transpose()
+                .ok()?,
+            code: (code != "-").then(|| code.parse()).transpose().ok()?,
+            id: fields.next()?.to_string(),
+            pid: match fields.next() {
+                None | Some("-") => None,
+                Some(pid) => Some(pid.parse().ok()?),
+            },
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Stats {
+    pub launches: u32,
+    pub total: u64,
+    pub last: u64,
+    /// Launches weighted by how recent they are, see `weight`.
+    pub frecency: u64,
+}
+
+fn path() -> Option<PathBuf> {
+    logs::data_dir().map(|dir| dir.join("history.tsv"))
+}
+
+/// Takes the lock that keeps `compact` from swapping the file out from under a run
+/// being recorded. It's a file of its own as the history file gets replaced. Held
+/// until the returned file is dropped.
+fn lock() -> io::Result<File> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = File::create(path.with_extension("lock"))?;
+    // SAFETY: flock only looks at the descriptor, which `file` keeps open.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+/// Appends `run` to the history file. Each run is a single small write to a file
+/// opened for appending, so separate launches can't interleave their lines. A run
+/// that has finished is written again with its duration and exit code, `load` keeps
+/// the last line for it.
+pub fn record(run: &Run) -> io::Result<()> {
+    let _lock = lock()?;
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(run.line().as_bytes())
+}
+
+/// Every recorded run, oldest first, and how many lines they were read from.
+fn read() -> (Vec<Run>, usize) {
+    match path().and_then(|path| fs::read_to_string(path).ok()) {
+        Some(contents) => merge(&contents),
+        None => (Vec::new(), 0),
+    }
+}
+
+/// The runs in the history file `contents`, with the start and finish lines of each
+/// run made into one, and how many lines they were read from. Lines that don't parse
+/// are skipped.
+fn merge(contents: &str) -> (Vec<Run>, usize) {
+    let mut runs: Vec<Run> = Vec::new();
+    // Where each run is in `runs`, to find it again when its finish comes along.
+    let mut seen: HashMap<(u64, Option<u32>, String), usize> = HashMap::new();
+    let mut lines = 0;
+    for run in contents.lines().filter_map(Run::parse) {
+        lines += 1;
+        let key = (run.started, run.pid, run.id.clone());
+        match seen.get(&key) {
+            Some(&i) => runs[i] = run,
+            None => {
+                seen.insert(key, runs.len());
+                runs.push(run);
+            }
+        }
+    }
+    (runs, lines)
+}
+
+/// Every recorded run, oldest first.
+pub fn load() -> Vec<Run> {
+    read().0
+}
+
+/// Rewrites the history file with one line per run, dropping the start lines of runs
+/// that have since finished.
+pub fn compact() -> io::Result<()> {
+    let _lock = lock()?;
+    let (runs, lines) = read();
+    if runs.len() == lines {
+        return Ok(());
+    }
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+    let temp = path.with_extension("tsv.new");
+    let contents: String = runs.iter().map(Run::line).collect();
+    fs::write(&temp, contents)?;
+    fs::rename(temp, path)
+}
+
+/// When the history file was last changed and how long it is, to tell whether it
+/// needs loading again.
+pub fn version() -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path()?).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Launch count, total time and last launch per entry id.
+pub fn stats(runs: &[Run]) -> HashMap<String, Stats> {
+    let now = clock::now();
+    let mut stats: HashMap<String, Stats> = HashMap::new();
+    for run in runs {
+        let entry = stats.entry(run.id.clone()).or_default();
+        entry.launches += 1;
+        entry.total += run.duration.unwrap_or_default();
+        entry.last = entry.last.max(run.started);
+        entry.frecency += weight(now.saturating_sub(run.started));
+    }
+    stats
+}
+
+/// How much a launch `age` seconds ago counts towards frecency, the same buckets
+/// Firefox uses for its address bar.
+fn weight(age: u64) -> u64 {
+    const DAY: u64 = 24 * 60 * 60;
+    match age / DAY {
+        0..=3 => 100,
+        4..=13 => 70,
+        14..=30 => 50,
+        31..=89 => 30,
+        _ => 10,
+    }
+}
+
+/// Ids of the most recently launched entries, newest first and without repeats.
+pub fn recent(runs: &[Run], count: usize) -> Vec<String> {
+    let mut recent: Vec<String> = Vec::new();
+    for run in runs.iter().rev() {
+        if recent.len() == count {
+            break;
+        }
+        if !recent.contains(&run.id) {
+            recent.push(run.id.clone());
+        }
+    }
+    recent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(started: u64, duration: Option<u64>, pid: Option<u32>) -> Run {
+        Run {
+            started,
+            duration,
+            code: duration.map(|_| 0),
+            id: "games/quake".to_string(),
+            pid,
+        }
+    }
+
+    #[test]
+    fn lines_read_back() {
+        let line = run(100, Some(5), Some(42)).line();
+        assert_eq!(line, "100\t5\t0\tgames/quake\t42\n");
+        let parsed = Run::parse(line.trim_end()).unwrap();
+        assert_eq!(parsed.duration, Some(5));
+        assert_eq!(parsed.pid, Some(42));
+
+        let started = Run::parse("100\t-\t-\tgames/quake\t-").unwrap();
+        assert_eq!(
+            (started.duration, started.code, started.pid),
+            (None, None, None)
+        );
+    }
+
+    #[test]
+    fn lines_from_before_pids_read_back() {
+        let old = Run::parse("100\t5\t0\tgames/quake").unwrap();
+        assert_eq!(old.id, "games/quake");
+        assert_eq!(old.pid, None);
+    }
+
+    #[test]
+    fn finishes_replace_their_start() {
+        let contents: String = [run(100, None, Some(42)), run(100, Some(5), Some(42))]
+            .iter()
+            .map(Run::line)
+            .collect();
+        let (runs, lines) = merge(&contents);
+        assert_eq!((runs.len(), lines), (1, 2));
+        assert_eq!(runs[0].duration, Some(5));
+    }
+
+    #[test]
+    fn runs_in_the_same_second_are_kept_apart() {
+        let contents: String = [
+            run(100, None, Some(42)),
+            run(100, None, Some(43)),
+            run(100, Some(5), Some(42)),
+            run(100, Some(7), Some(43)),
+        ]
+        .iter()
+        .map(Run::line)
+        .collect();
+        let (runs, lines) = merge(&contents);
+        assert_eq!(lines, 4);
+        let durations: Vec<_> = runs.iter().map(|run| run.duration).collect();
+        assert_eq!(durations, [Some(5), Some(7)]);
+        assert_eq!(stats(&runs)["games/quake"].launches, 2);
+    }
+}
@@ -0,0 +1,529 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Everything that can be bound to a key in the list.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Top,
+    Bottom,
+    Collapse,
+    Expand,
+    Fold,
+    Search,
+    Pin,
+    Select,
+    LaunchStayOpen,
+    StayOpen,
+    Log,
+    Sort,
+    Add,
+    Edit,
+    OpenEditor,
+    Delete,
+    Errors,
+    Tags,
+    Help,
+}
+
+impl Action {
+    /// In the order they're listed in the help.
+    pub const ALL: [Action; 22] = [
+        Action::Select,
+        Action::LaunchStayOpen,
+        Action::Pin,
+        Action::Up,
+        Action::Down,
+        Action::Top,
+        Action::Bottom,
+        Action::Collapse,
+        Action::Expand,
+        Action::Fold,
+        Action::Search,
+        Action::Tags,
+        Action::Sort,
+        Action::StayOpen,
+        Action::Log,
+        Action::Add,
+        Action::Edit,
+        Action::OpenEditor,
+        Action::Delete,
+        Action::Errors,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// The name used for it in `[keys]`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::Collapse => "collapse",
+            Action::Expand => "expand",
+            Action::Fold => "fold",
+            Action::Search => "search",
+            Action::Pin => "pin",
+            Action::Select => "select",
+            Action::LaunchStayOpen => "launch-stay-open",
+            Action::StayOpen => "stay-open",
+            Action::Log => "log",
+            Action::Sort => "sort",
+            Action::Add => "add",
+            Action::Edit => "edit",
+            Action::OpenEditor => "open-editor",
+            Action::Delete => "delete",
+            Action::Errors => "errors",
+            Action::Tags => "tags",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::Top => "Go to the top",
+            Action::Bottom => "Go to the bottom",
+            Action::Collapse => "Collapse the category",
+            Action::Expand => "Expand the category",
+            Action::Fold => "Fold or unfold the category",
+            Action::Search => "Search",
+            Action::Pin => "Pin or unpin the entry",
+            Action::Select => "Launch, or fold a category",
+            Action::LaunchStayOpen => "Launch and stay open",
+            Action::StayOpen => "Toggle staying open after launching",
+            Action::Log => "Show the last log",
+            Action::Sort => "Change the sort order",
+            Action::Add => "Add an entry",
+            Action::Edit => "Edit the entry",
+            Action::OpenEditor => "Open the entry's file in $EDITOR",
+            Action::Delete => "Delete the entry",
+            Action::Errors => "Show config problems",
+            Action::Tags => "Filter by tags",
+            Action::Help => "Show this help",
+        }
+    }
+
+    fn defaults(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::Up => &["up", "k"],
+            Action::Down => &["down", "j"],
+            Action::Top => &["home", "g g"],
+            Action::Bottom => &["end", "G"],
+            Action::Collapse => &["left", "h"],
+            Action::Expand => &["right", "l"],
+            Action::Fold => &["space"],
+            Action::Search => &["/"],
+            Action::Pin => &["p"],
+            Action::Select => &["enter"],
+            Action::LaunchStayOpen => &["o"],
+            Action::StayOpen => &["s"],
+            Action::Log => &["v"],
+            Action::Sort => &["tab"],
+            Action::Add => &["a"],
+            Action::Edit => &["e"],
+            Action::OpenEditor => &["E"],
+            Action::Delete => &["d"],
+            Action::Errors => &["!"],
+            Action::Tags => &["t"],
+            Action::Help => &["?"],
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// A single key press with its modifiers.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Terminals report shift as part of the character (`G`) and sometimes as a modifier
+    /// as well, so it's dropped for characters to make `G` and `shift-g` the same key.
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Key { code, modifiers }
+    }
+
+    /// Parses a key like `j`, `ctrl-n`, `shift-enter` or `f5`.
+    fn parse(text: &str) -> Result<Key, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        loop {
+            let (modifier, after) = match rest.split_once('-') {
+                Some((modifier, after)) if !after.is_empty() => (modifier, after),
+                _ => break,
+            };
+            match modifier.to_lowercase().as_str() {
+                "ctrl" => modifiers |= KeyModifiers::CONTROL,
+                "alt" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                _ => break,
+            }
+            rest = after;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "esc" | "escape" => KeyCode::Esc,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key `{}`", text)),
+                },
+            },
+        };
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Key {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::BackTab => write!(f, "shift-tab"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+/// One or more keys pressed one after the other, written space separated like `g g`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Chord(Vec<Key>);
+
+impl Chord {
+    fn parse(text: &str) -> Result<Chord, String> {
+        let keys = text
+            .split_whitespace()
+            .map(Key::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err("empty key binding".to_string());
+        }
+        Ok(Chord(keys))
+    }
+
+    fn starts_with(&self, keys: &[Key]) -> bool {
+        self.0.starts_with(keys)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(Key::to_string).collect();
+        write!(f, "{}", keys.join(" "))
+    }
+}
+
+pub enum Lookup {
+    Action(Action),
+    /// The keys so far are the start of a longer chord, wait for the next one.
+    Pending,
+    None,
+}
+
+/// Which chords trigger which action. Built from the defaults with `[keys]` from the
+/// settings on top.
+pub struct Keymap {
+    bindings: Vec<(Chord, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::new(&toml::Table::new()).0
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap from `[keys]`, where each action maps to a chord or a list of
+    /// them and replaces its default keys. Problems come back as messages, including
+    /// chords that clash with another action's, where one is the same as or the start of
+    /// the other. The first one wins, and those set in `[keys]` come before the defaults.
+    pub fn new(keys: &toml::Table) -> (Keymap, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut custom = Vec::new();
+        for (name, value) in keys {
+            let Some(action) = Action::from_name(name) else {
+                errors.push(format!("unknown action `{}` in [keys]", name));
+                continue;
+            };
+            let texts: Option<Vec<&str>> = match value {
+                toml::Value::String(text) => Some(vec![text]),
+                toml::Value::Array(values) => values.iter().map(|v| v.as_str()).collect(),
+                _ => None,
+            };
+            let Some(texts) = texts else {
+                errors.push(format!(
+                    "keys for `{}` should be a string or a list of strings",
+                    name
+                ));
+                continue;
+            };
+            let mut chords = Vec::new();
+            for text in texts {
+                match Chord::parse(text) {
+                    Ok(chord) => chords.push(chord),
+                    Err(e) => errors.push(format!("{} for `{}`", e, name)),
+                }
+            }
+            custom.push((action, chords));
+        }
+
+        let mut wanted: Vec<(Chord, Action)> = Vec::new();
+        for (action, chords) in &custom {
+            wanted.extend(chords.iter().map(|chord| (chord.clone(), *action)));
+        }
+        for action in Action::ALL {
+            if custom.iter().any(|(custom, _)| *custom == action) {
+                continue;
+            }
+            for text in action.defaults() {
+                let chord = Chord::parse(text).expect("default key bindings parse");
+                wanted.push((chord, action));
+            }
+        }
+
+        let mut bindings: Vec<(Chord, Action)> = Vec::new();
+        for (chord, action) in wanted {
+            let clash = bindings.iter().find(|(other, other_action)| {
+                *other_action != action
+                    && (other.starts_with(&chord.0) || chord.starts_with(&other.0))
+            });
+            match clash {
+                Some((other, other_action)) => errors.push(format!(
+                    "`{}` for {} clashes with `{}` for {} and is ignored",
+                    chord,
+                    action.name(),
+                    other,
+                    other_action.name()
+                )),
+                None => bindings.push((chord, action)),
+            }
+        }
+
+        (Keymap { bindings }, errors)
+    }
+
+    /// What `keys`, pressed in order, do.
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut pending = false;
+        for (chord, action) in &self.bindings {
+            if chord.0 == keys {
+                return Lookup::Action(*action);
+            }
+            pending |= chord.starts_with(keys);
+        }
+        if pending {
+            Lookup::Pending
+        } else {
+            Lookup::None
+        }
+    }
+
+    /// Every chord bound to `action`, for the help.
+    pub fn chords(&self, action: Action) -> Vec<&Chord> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(chord, _)| chord)
+            .collect()
+    }
+
+    /// The first key bound to `action` to mention in hints, e.g. "press ? for help".
+    pub fn hint(&self, action: Action) -> String {
+        self.chords(action)
+            .first()
+            .map_or_else(|| action.name().to_string(), |chord| chord.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    fn keymap(toml: &str) -> (Keymap, Vec<String>) {
+        Keymap::new(&toml.parse().unwrap())
+    }
+
+    #[test]
+    fn parses_plain_and_named_keys() {
+        assert_eq!(
+            Key::parse("j"),
+            Ok(key(KeyCode::Char('j'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            Key::parse("space"),
+            Ok(key(KeyCode::Char(' '), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            Key::parse("Enter"),
+            Ok(key(KeyCode::Enter, KeyModifiers::NONE))
+        );
+        assert_eq!(Key::parse("f5"), Ok(key(KeyCode::F(5), KeyModifiers::NONE)));
+        assert!(Key::parse("f13").is_err());
+        assert!(Key::parse("nope").is_err());
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        let ctrl_alt = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        assert_eq!(
+            Key::parse("ctrl-alt-x"),
+            Ok(key(KeyCode::Char('x'), ctrl_alt))
+        );
+        assert_eq!(
+            Key::parse("shift-enter"),
+            Ok(key(KeyCode::Enter, KeyModifiers::SHIFT))
+        );
+        assert!(Key::parse("hyper-x").is_err());
+    }
+
+    #[test]
+    fn parses_dash_as_a_key() {
+        assert_eq!(
+            Key::parse("-"),
+            Ok(key(KeyCode::Char('-'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            Key::parse("ctrl--"),
+            Ok(key(KeyCode::Char('-'), KeyModifiers::CONTROL))
+        );
+    }
+
+    #[test]
+    fn shift_is_part_of_the_character() {
+        let g = key(KeyCode::Char('G'), KeyModifiers::NONE);
+        assert_eq!(Key::parse("shift-g"), Ok(g));
+        assert_eq!(Key::parse("G"), Ok(g));
+        assert_eq!(Key::new(KeyCode::Char('G'), KeyModifiers::SHIFT), g);
+    }
+
+    #[test]
+    fn shift_tab_is_back_tab() {
+        let back_tab = key(KeyCode::BackTab, KeyModifiers::NONE);
+        assert_eq!(Key::parse("shift-tab"), Ok(back_tab));
+        assert_eq!(Key::new(KeyCode::BackTab, KeyModifiers::SHIFT), back_tab);
+    }
+
+    #[test]
+    fn parses_chords() {
+        let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+        assert_eq!(Chord::parse("g  g"), Ok(Chord(vec![g, g])));
+        assert!(Chord::parse("  ").is_err());
+        assert!(Chord::parse("g nope").is_err());
+    }
+
+    #[test]
+    fn looks_up_chords_key_by_key() {
+        let (keymap, errors) = keymap("");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+        assert!(matches!(keymap.lookup(&[g]), Lookup::Pending));
+        assert!(matches!(
+            keymap.lookup(&[g, g]),
+            Lookup::Action(Action::Top)
+        ));
+        let x = key(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert!(matches!(keymap.lookup(&[x]), Lookup::None));
+    }
+
+    #[test]
+    fn custom_keys_replace_the_defaults() {
+        let (keymap, errors) = keymap("quit = [\"ctrl-q\", \"Q\"]");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let chords: Vec<String> = keymap
+            .chords(Action::Quit)
+            .iter()
+            .map(|chord| chord.to_string())
+            .collect();
+        assert_eq!(chords, ["ctrl-q", "Q"]);
+        let q = key(KeyCode::Char('q'), KeyModifiers::NONE);
+        assert!(matches!(keymap.lookup(&[q]), Lookup::None));
+    }
+
+    #[test]
+    fn a_key_that_starts_a_chord_clashes() {
+        // `g` on its own would make `g g` unreachable, the custom one wins.
+        let (keymap, errors) = keymap("search = \"g\"");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("`g g` for top"), "{}", errors[0]);
+        let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+        assert!(matches!(
+            keymap.lookup(&[g]),
+            Lookup::Action(Action::Search)
+        ));
+    }
+
+    #[test]
+    fn the_same_key_twice_clashes() {
+        let (keymap, errors) = keymap("pin = \"j\"");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        let j = key(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert!(matches!(keymap.lookup(&[j]), Lookup::Action(Action::Pin)));
+    }
+
+    #[test]
+    fn reports_bad_entries() {
+        let (_, errors) = keymap("fly = \"x\"\nquit = 5\nhelp = \"nope\"");
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock,
+    config::{self, Program},
+    history, logs,
+    settings::Settings,
+};
+
+// The shell itself always starts, so a missing or non executable program only shows up as
+// the shell's exit status. This is how long we wait for that before calling it a success.
+// Programs run without a shell fail to start straight away instead.
+const GRACE: Duration = Duration::from_millis(200);
+
+/// Everything the supervisor needs to run an entry, handed to it on stdin.
+#[derive(Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub title: String,
+    /// The program to run followed by its arguments.
+    pub argv: Vec<String>,
+    /// Whether `argv` is the shell running the entry's `command`.
+    pub shell: bool,
+    pub cwd: Option<PathBuf>,
+    pub clear_env: bool,
+    pub env_remove: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// Shell the hooks are run with.
+    pub hook_shell: String,
+    /// Where hooks run when `cwd` doesn't exist, e.g. before a `pre_launch` mounts it.
+    pub hook_dir: Option<PathBuf>,
+    /// Run before `argv`, the first one that fails stops the launch.
+    pub pre_launch: Vec<String>,
+    /// Run after `argv` exits, or fails to start, with its exit code.
+    pub post_exit: Vec<String>,
+}
+
+impl Job {
+    pub fn new(program: &Program, settings: &Settings) -> Job {
+        Job {
+            id: program.id(),
+            title: program.title.clone(),
+            argv: program.argv(&settings.shell),
+            shell: program.args.is_empty(),
+            cwd: program.working_dir(),
+            clear_env: program.clear_env,
+            env_remove: program.env_remove.clone(),
+            env: program.env.clone(),
+            hook_shell: settings.shell.clone(),
+            hook_dir: config::path(),
+            pre_launch: program
+                .pre_launch
+                .clone()
+                .unwrap_or_else(|| settings.pre_launch.clone()),
+            post_exit: program
+                .post_exit
+                .clone()
+                .unwrap_or_else(|| settings.post_exit.clone()),
+        }
+    }
+
+    fn command(&self) -> io::Result<Command> {
+        if let Some(cwd) = &self.cwd {
+            // Otherwise this shows up as the program itself not being found.
+            if !cwd.is_dir() {
+                return Err(io::Error::other(format!(
+                    "the directory {} doesn't exist",
+                    cwd.display()
+                )));
+            }
+        }
+        self.command_for(&self.argv, self.cwd.as_deref())
+    }
+
+    /// Runs `argv` in `dir` with the entry's environment.
+    fn command_for(&self, argv: &[String], dir: Option<&Path>) -> io::Result<Command> {
+        let (program, args) = argv
+            .split_first()
+            .ok_or_else(|| io::Error::other("nothing to run"))?;
+        let mut cmd = Command::new(program);
+        cmd.args(args);
+        if let Some(dir) = dir {
+            cmd.current_dir(dir);
+        }
+        if self.clear_env {
+            cmd.env_clear();
+        }
+        for name in &self.env_remove {
+            cmd.env_remove(name);
+        }
+        cmd.envs(&self.env);
+        Ok(cmd)
+    }
+
+    /// Runs one of the hooks and waits for it. It gets the entry's id and title, and its
+    /// exit code for `post_exit`, in `GLAUNCHER_*` variables. `stdio` says where its input
+    /// and output go.
+    fn run_hook(
+        &self,
+        hook: &str,
+        exit_code: Option<i32>,
+        stdio: impl Fn(&mut Command),
+    ) -> io::Result<()> {
+        let argv = [self.hook_shell.clone(), "-c".to_string(), hook.to_string()];
+        // The entry's directory may be what `pre_launch` is there to mount or make.
+        let dir = match &self.cwd {
+            Some(cwd) if cwd.is_dir() => Some(cwd.as_path()),
+            _ => self.hook_dir.as_deref().filter(|dir| dir.is_dir()),
+        };
+        let mut cmd = self.command_for(&argv, dir)?;
+        cmd.env("GLAUNCHER_ENTRY_ID", &self.id)
+            .env("GLAUNCHER_ENTRY_TITLE", &self.title);
+        if let Some(code) = exit_code {
+            cmd.env("GLAUNCHER_EXIT_CODE", code.to_string());
+        }
+        stdio(&mut cmd);
+        let status = cmd.status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("`{}` failed, {}", hook, status)));
+        }
+        Ok(())
+    }
+
+    /// Runs the `pre_launch` hooks in turn, stopping at the first one that fails.
+    fn run_pre_launch(&self, stdio: impl Fn(&mut Command)) -> io::Result<()> {
+        for hook in &self.pre_launch {
+            self.run_hook(hook, None, &stdio)
+                .map_err(|e| io::Error::other(format!("the pre_launch hook {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Runs every `post_exit` hook, even after one fails as they're usually cleaning up.
+    /// Returns the problems.
+    fn run_post_exit(&self, exit_code: i32, stdio: impl Fn(&mut Command)) -> Vec<String> {
+        self.post_exit
+            .iter()
+            .filter_map(|hook| self.run_hook(hook, Some(exit_code), &stdio).err())
+            .map(|e| format!("the post_exit hook {}", e))
+            .collect()
+    }
+}
+
+/// Starts `job` fully detached from the launcher: it gets its own session and never
+/// touches our terminal. Returns the pid once it's running.
+///
+/// The program is run by a copy of ourselves started with `--supervise`, which sticks
+/// around after we quit to capture the output and exit status.
+pub fn spawn(job: &Job) -> io::Result<u32> {
+    let mut cmd = Command::new(env::current_exe()?);
+    cmd.arg("--supervise")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    // SAFETY: setsid is async-signal-safe, which is all pre_exec asks for.
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = cmd.spawn()?;
+
+    let sent = match child.stdin.take() {
+        Some(mut stdin) => toml::to_string(job)
+            .map_err(io::Error::other)
+            .and_then(|spec| stdin.write_all(spec.as_bytes())),
+        None => Ok(()),
+    };
+    let mut reply = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        BufReader::new(stdout).read_line(&mut reply)?;
+    }
+    // Reap it in the background so it doesn't linger as a zombie while we're running.
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    sent?;
+
+    match reply.trim_end().split_once(' ') {
+        Some(("pid", pid)) => pid.parse().map_err(io::Error::other),
+        Some(("error", message)) => Err(io::Error::other(message.to_string())),
+        _ => Err(io::Error::other(
+            "the launch supervisor exited unexpectedly",
+        )),
+    }
+}
+
+/// Sends `line` back to the launcher. It may have quit while `pre_launch` was running, so
+/// a closed pipe is ignored rather than cutting the run short.
+fn reply(line: &str) {
+    let mut stdout = io::stdout();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+/// Body of `glauncher --supervise`. Reads a `Job` from stdin, runs it with its output
+/// going to a log file, and reports `pid <pid>` or `error <message>` on stdout once it
+/// has either started or failed to. Returns the program's exit code.
+pub fn supervise() -> i32 {
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut input) {
+        reply(&format!("error {}", e));
+        return 1;
+    }
+    let job: Job = match toml::from_str(&input) {
+        Ok(job) => job,
+        Err(e) => {
+            reply(&format!("error {}", e));
+            return 1;
+        }
+    };
+
+    let mut log = logs::create(&job.id).ok().map(|(_, file)| file);
+    let started = Instant::now();
+    let started_at = clock::now();
+    if let Some(file) = &mut log {
+        let _ = writeln!(
+            file,
+            "--- started {}: {}",
+            clock::format(clock::now()),
+            config::join_args(&job.argv)
+        );
+    }
+    let output = || match &log {
+        Some(file) => file.try_clone().map(Stdio::from).unwrap_or(Stdio::null()),
+        None => Stdio::null(),
+    };
+    let note = |message: &str| {
+        if let Some(mut file) = log.as_ref() {
+            let _ = writeln!(file, "--- {}", message);
+        }
+    };
+    let to_log = |cmd: &mut Command| {
+        cmd.stdin(Stdio::null()).stdout(output()).stderr(output());
+    };
+
+    if let Err(e) = job.run_pre_launch(to_log) {
+        note(&e.to_string());
+        reply(&format!("error {}", e));
+        return 1;
+    }
+    let spawned = job.command().and_then(|mut cmd| {
+        cmd.stdin(Stdio::null())
+            .stdout(output())
+            .stderr(output())
+            .spawn()
+    });
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            reply(&format!("error {}", e));
+            // Like a shell does for a program it couldn't run.
+            for problem in job.run_post_exit(127, to_log) {
+                note(&problem);
+            }
+            return 1;
+        }
+    };
+
+    let mut run = history::Run {
+        started: started_at,
+        duration: None,
+        code: None,
+        id: job.id.clone(),
+        pid: Some(child.id()),
+    };
+    let _ = history::record(&run);
+
+    let mut status = None;
+    while status.is_none() && started.elapsed() < GRACE {
+        status = child.try_wait().ok().flatten();
+        thread::sleep(Duration::from_millis(10));
+    }
+    match status.and_then(|status| status.code()) {
+        Some(127) if job.shell => reply("error command not found"),
+        Some(126) if job.shell => reply("error permission denied"),
+        _ => reply(&format!("pid {}", child.id())),
+    }
+
+    let status = match status {
+        Some(status) => Ok(status),
+        None => child.wait(),
+    };
+    let code = status.as_ref().ok().and_then(|s| s.code()).unwrap_or(1);
+    run.duration = Some(started.elapsed().as_secs());
+    run.code = Some(code);
+    let _ = history::record(&run);
+    let status = match &status {
+        Ok(status) => status.to_string(),
+        Err(e) => e.to_string(),
+    };
+    note(&format!(
+        "{} at {} after {}",
+        status,
+        clock::format(clock::now()),
+        clock::format_duration(started.elapsed().as_secs())
+    ));
+    for problem in job.run_post_exit(code, to_log) {
+        note(&problem);
+    }
+    code
+}
+
+/// Runs `job` attached to our terminal and waits for it to finish. The caller has to
+/// hand the terminal over first. Problems with the `post_exit` hooks are returned next to
+/// the program's exit status, for the caller to show once it has the terminal back.
+pub fn run_foreground(job: &Job) -> (io::Result<ExitStatus>, Vec<String>) {
+    // Hooks share the terminal too, so they can ask for a password.
+    let attached = |_: &mut Command| {};
+    if let Err(e) = job.run_pre_launch(attached) {
+        return (Err(e), Vec::new());
+    }
+    let started = Instant::now();
+    let status = job
+        .command()
+        .and_then(|mut cmd| cmd.spawn())
+        .and_then(|mut child| {
+            let mut run = history::Run {
+                started: clock::now(),
+                duration: None,
+                code: None,
+                id: job.id.clone(),
+                pid: Some(child.id()),
+            };
+            let _ = history::record(&run);
+            let status = child.wait()?;
+            run.duration = Some(started.elapsed().as_secs());
+            run.code = Some(status.code().unwrap_or(1));
+            let _ = history::record(&run);
+            Ok(status)
+        });
+    let code = match &status {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(_) => 127,
+    };
+    let problems = job.run_post_exit(code, attached);
+    (status, problems)
+}
@@ -0,0 +1,79 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::clock;
+
+// How many runs to keep per entry.
+const KEEP: usize = 5;
+// Only the end of a log is ever shown, don't read more than this of it.
+const TAIL_BYTES: u64 = 64 * 1024;
+
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("glauncher"))
+}
+
+/// Folder holding the logs of the entry with the given id.
+fn dir(id: &str) -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("logs").join(id))
+}
+
+/// Creates the log file for a new run of `id`, deleting the oldest runs past `KEEP`.
+pub fn create(id: &str) -> io::Result<(PathBuf, File)> {
+    let dir = dir(id).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+    fs::create_dir_all(&dir)?;
+
+    let mut old = runs(&dir);
+    while old.len() >= KEEP {
+        let _ = fs::remove_file(old.remove(0));
+    }
+
+    // With our pid, as the same entry can be started twice in a second.
+    let path = dir.join(format!("{}-{}.log", clock::now(), std::process::id()));
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    Ok((path, file))
+}
+
+/// Log files in `dir`, oldest first.
+fn runs(dir: &Path) -> Vec<PathBuf> {
+    let mut runs: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    runs.sort();
+    runs
+}
+
+/// When the run logged to `path` was started, going by its name.
+pub fn started(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    // Logs from before the pid was added are named by just the time.
+    let time = stem.split_once('-').map_or(stem, |(time, _)| time);
+    time.parse().ok()
+}
+
+/// Most recent log of the entry with the given id.
+pub fn latest(id: &str) -> Option<PathBuf> {
+    runs(&dir(id)?).pop()
+}
+
+/// The last part of the log at `path`.
+pub fn tail(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes);
+    // Don't start halfway through a line.
+    Ok(match text.find('\n') {
+        Some(i) if start > 0 => text[i + 1..].to_string(),
+        _ => text.into_owned(),
+    })
+}
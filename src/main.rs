@@ -1,20 +1,125 @@
 use std::{
-    fs::{self, read_to_string},
-    io::{self, stdout},
-    path::PathBuf,
-    process::Command,
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, stdout, Stdout},
+    path::{Path, PathBuf},
+    sync::mpsc,
     thread,
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
+use clap::Parser;
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
-use fork::{fork, Fork};
 use ratatui::{prelude::*, widgets::*};
-use serde::{Deserialize, Serialize};
+
+mod cli;
+mod clock;
+mod config;
+mod form;
+mod history;
+mod keys;
+mod launch;
+mod logs;
+mod search;
+mod settings;
+mod sort;
+mod state;
+mod tags;
+mod theme;
+mod vars;
+mod watch;
+
+use config::Program;
+use keys::{Action, Lookup};
+
+#[derive(Default, PartialEq)]
+enum Mode {
+    #[default]
+    Normal,
+    Search,
+    Errors,
+    Form,
+    ConfirmDelete,
+    Help,
+    Tags,
+}
+
+/// Something that needs the terminal to itself for a while.
+enum Suspend {
+    /// Run this in the foreground.
+    Run(Box<launch::Job>),
+    /// Open this config file in the user's editor.
+    Edit(PathBuf),
+}
+
+/// What submitting the entry form does.
+enum Editing {
+    /// Create a new file in this category.
+    New(String),
+    /// Overwrite the file of an entry. Kept by path rather than index as the list can be
+    /// reloaded under the open form.
+    Existing { category: String, source: PathBuf },
+}
+
+/// A background launch waiting on the supervisor, which runs the `pre_launch` hooks
+/// before it reports back.
+struct Starting {
+    title: String,
+    stay_open: bool,
+    result: mpsc::Receiver<io::Result<u32>>,
+}
+
+/// An entry waiting on answers to its params before it's launched. Kept by file and
+/// position in it, like `Editing::Existing`, as the list can be reloaded under the form.
+struct Launching {
+    source: PathBuf,
+    index: Option<usize>,
+    stay_open: bool,
+}
+
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+// Runs are recorded by the supervisor processes, so pick up what they've written now and then.
+const HISTORY_REFRESH: Duration = Duration::from_secs(2);
+const RECENT_COUNT: usize = 5;
+
+/// A line in the left-hand list.
+#[derive(Clone, PartialEq)]
+enum Row {
+    Group(Group),
+    GroupEntry(Group, usize),
+    Category(String),
+    Entry(usize),
+}
+
+impl Row {
+    /// Index into `GlobalInfo::list` of the entry on this row.
+    fn entry(&self) -> Option<usize> {
+        match self {
+            Row::GroupEntry(_, index) | Row::Entry(index) => Some(*index),
+            _ => None,
+        }
+    }
+}
+
+/// Sections at the top of the list that pull entries out of their categories.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Group {
+    Pinned,
+    Recent,
+}
+
+impl Group {
+    fn name(self) -> &'static str {
+        match self {
+            Group::Pinned => "Pinned",
+            Group::Recent => "Recent",
+        }
+    }
+}
 
 #[derive(Default)]
 struct GlobalInfo {
@@ -22,152 +127,1450 @@ struct GlobalInfo {
     list: Vec<Program>,
     liststate: ListState,
     list_pos: usize,
+    mode: Mode,
+    query: String,
+    hits: Vec<search::Hit>,
+    // Where the cursor was before searching, so Esc can put it back.
+    saved_pos: usize,
+    collapsed: HashSet<String>,
+    collapsed_groups: HashSet<Group>,
+    // Shown in a popup over everything else until a key is pressed.
+    error: Option<String>,
+    // Keep running after launching something instead of quitting.
+    stay_open: bool,
+    // Message for the status bar and when it was posted.
+    toast: Option<(String, Instant)>,
+    // Set by the event handler, which can't get at the terminal, and picked up by the
+    // main loop.
+    suspend: Option<Suspend>,
+    // Show the selected entry's last log instead of its description.
+    show_log: bool,
+    stats: HashMap<String, history::Stats>,
+    // Ids of the last few entries launched, newest first.
+    recent: Vec<String>,
+    history_loaded: Option<Instant>,
+    // Modified time and length of the history file when it was loaded.
+    history_version: Option<(SystemTime, u64)>,
+    state: state::State,
+    settings: settings::Settings,
+    // Files and entries that failed to load.
+    errors: Vec<config::LoadError>,
+    errors_scroll: u16,
+    form: Option<form::Form>,
+    editing: Option<Editing>,
+    launching: Option<Launching>,
+    starting: Vec<Starting>,
+    // Keys pressed so far of a chord like `g g`.
+    pending: Vec<keys::Key>,
+    tag_filter: tags::Filter,
+    // Cursor in the tag picker.
+    tag_pos: usize,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Program {
-    title: String,
-    description: String,
-    command: String,
+impl GlobalInfo {
+    fn notify(&mut self, message: String) {
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// The sort picked with Tab, or the one from the settings if none has been yet.
+    fn sort(&self) -> sort::Sort {
+        self.state.sort.unwrap_or(self.settings.sort)
+    }
+
+    /// Loads the history again if it has changed since it was last loaded.
+    fn reload_history(&mut self) {
+        self.history_loaded = Some(Instant::now());
+        let version = history::version();
+        if version.is_some() && version == self.history_version {
+            return;
+        }
+        self.history_version = version;
+        let runs = history::load();
+        self.stats = history::stats(&runs);
+        self.recent = history::recent(&runs, RECENT_COUNT);
+    }
+
+    /// Search results for the current query, narrowed down by the tag filter.
+    fn search(&self) -> Vec<search::Hit> {
+        let mut hits = search::filter(&self.list, &self.query);
+        hits.retain(|hit| self.tag_filter.matches(&self.list[hit.index]));
+        hits
+    }
+
+    /// The lines currently shown in the list, in display order. The pinned and recently
+    /// launched groups come first, then top level entries, then each category with its entries
+    /// unless it or a parent is collapsed. Searching flattens everything into ranked hits.
+    /// Entries that don't match the tag filter are left out, along with categories that
+    /// end up empty.
+    fn rows(&self) -> Vec<Row> {
+        if self.mode == Mode::Search {
+            return self.hits.iter().map(|hit| Row::Entry(hit.index)).collect();
+        }
+        let mut categories: Vec<&str> = Vec::new();
+        for program in self.list.iter().filter(|p| self.tag_filter.matches(p)) {
+            // Folders holding only subfolders still get a row to fold them with.
+            let category = program.category.as_str();
+            let parents = category.match_indices('/').map(|(i, _)| &category[..i]);
+            for category in parents.chain([category]) {
+                if !categories.contains(&category) {
+                    categories.push(category);
+                }
+            }
+        }
+        // By folder, so `games/vr` stays right under `games` rather than after `games-old`.
+        categories.sort_by(|a, b| a.split('/').cmp(b.split('/')));
+
+        let order = sort::sorted(&self.list, &self.stats, self.sort());
+        let mut rows = Vec::new();
+        for group in [Group::Pinned, Group::Recent] {
+            let entries = self.group_entries(group);
+            if entries.is_empty() {
+                continue;
+            }
+            rows.push(Row::Group(group));
+            if !self.collapsed_groups.contains(&group) {
+                rows.extend(entries.into_iter().map(|i| Row::GroupEntry(group, i)));
+            }
+        }
+        for category in categories {
+            if self.is_hidden(category) {
+                continue;
+            }
+            if !category.is_empty() {
+                rows.push(Row::Category(category.to_string()));
+                if self.collapsed.contains(category) {
+                    continue;
+                }
+            }
+            for &index in &order {
+                let program = &self.list[index];
+                if program.category == category && self.tag_filter.matches(program) {
+                    rows.push(Row::Entry(index));
+                }
+            }
+        }
+        rows
+    }
+
+    /// Indexes into `list` of the entries listed under `group`.
+    fn group_entries(&self, group: Group) -> Vec<usize> {
+        let ids = match group {
+            Group::Pinned => &self.state.pinned,
+            Group::Recent => &self.recent,
+        };
+        ids.iter()
+            .filter_map(|id| self.list.iter().position(|program| program.id() == *id))
+            .filter(|&index| self.tag_filter.matches(&self.list[index]))
+            .collect()
+    }
+
+    /// Whether a parent of `category` is collapsed.
+    fn is_hidden(&self, category: &str) -> bool {
+        category
+            .match_indices('/')
+            .any(|(i, _)| self.collapsed.contains(&category[..i]))
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.rows().get(self.list_pos).cloned()
+    }
+
+    fn selected(&self) -> Option<&Program> {
+        self.selected_row()
+            .and_then(|row| row.entry())
+            .map(|index| &self.list[index])
+    }
 }
 
 fn main() -> io::Result<()> {
+    let cli = cli::Cli::parse();
+    if cli.supervise {
+        std::process::exit(launch::supervise());
+    }
+    if let Some(command) = cli.command {
+        std::process::exit(cli::run(command));
+    }
+
+    // Put the terminal back before the panic message is printed, otherwise it ends up
+    // on the alternate screen and the shell is left in raw mode.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        hook(info);
+    }));
+
+    let mut data = handle_setup();
+    data.stay_open = cli.stay_open || data.settings.stay_open;
+
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
-    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    let result = Terminal::new(CrosstermBackend::new(stdout()))
+        .and_then(|mut terminal| run(&mut terminal, &mut data));
+    restore_terminal();
+    result
+}
 
-    let mut data = handle_setup();
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = stdout().execute(LeaveAlternateScreen);
+}
+
+fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>, data: &mut GlobalInfo) -> io::Result<()> {
+    let watcher = match &data.config_path {
+        Some(config_path) => match watch::ConfigWatcher::new(config_path) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                data.notify(format!("Not watching the config for changes. {}", e));
+                None
+            }
+        },
+        None => None,
+    };
 
     let mut should_quit = false;
     while !should_quit {
-        terminal.draw(|f| ui(f, &mut data))?;
-        should_quit = handle_events(&mut data).unwrap();
+        terminal.draw(|f| ui(f, data))?;
+        should_quit = handle_events(data)?;
+        should_quit |= check_starting(data);
+        match data.suspend.take() {
+            Some(Suspend::Run(job)) => run_foreground(terminal, data, *job)?,
+            Some(Suspend::Edit(path)) => edit_file(terminal, data, path)?,
+            None => {}
+        }
+        if data
+            .history_loaded
+            .is_none_or(|loaded| loaded.elapsed() > HISTORY_REFRESH)
+        {
+            data.reload_history();
+        }
+        for change in watcher.iter().flat_map(|watcher| watcher.changes()) {
+            match change {
+                watch::Change::File(path) => reload_file(data, &path),
+                watch::Change::Tree => reload_config(data, None),
+            }
+        }
     }
+    Ok(())
+}
+
+/// Hands the terminal over to an entry with `terminal = true` and takes it back once the
+/// program exits.
+fn run_foreground(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    data: &mut GlobalInfo,
+    job: launch::Job,
+) -> io::Result<()> {
+    let (status, problems) = suspend(terminal, || launch::run_foreground(&job))?;
+    match status {
+        Err(e) => data.error = Some(format!("Could not start {}. {}", job.title, e)),
+        // Anything the hooks printed is gone with the normal screen, so this has to stay up.
+        Ok(_) if !problems.is_empty() => {
+            data.error = Some(format!(
+                "{} exited, but {}",
+                job.title,
+                problems.join(" and ")
+            ))
+        }
+        Ok(status) if status.success() => {}
+        Ok(status) => data.notify(format!("{} exited with {}", job.title, status)),
+    }
+    data.reload_history();
+    Ok(())
+}
 
+/// Opens `path` in `$VISUAL` or `$EDITOR`, then loads just that file again.
+fn edit_file(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    data: &mut GlobalInfo,
+    path: PathBuf,
+) -> io::Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // Through the shell so editors set up with arguments, like `code --wait`, work.
+    let status = suspend(terminal, || {
+        std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg("sh")
+            .arg(&path)
+            .status()
+    })?;
+    match status {
+        Ok(status) if !status.success() => {
+            data.notify(format!("{} exited with {}", editor, status));
+        }
+        Err(e) => data.error = Some(format!("Could not start {}. {}", editor, e)),
+        Ok(_) => {}
+    }
+    reload_file(data, &path);
+    Ok(())
+}
+
+/// Leaves the TUI, runs `f` with the terminal back in its normal state, then restores
+/// the TUI and forces a full redraw.
+fn suspend<T>(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    f: impl FnOnce() -> T,
+) -> io::Result<T> {
     disable_raw_mode()?;
     stdout().execute(LeaveAlternateScreen)?;
-    Ok(())
+    terminal.show_cursor()?;
+    let result = f();
+    stdout().execute(EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    terminal.clear()?;
+    Ok(result)
 }
 
 fn handle_events(data: &mut GlobalInfo) -> io::Result<bool> {
     if event::poll(std::time::Duration::from_millis(50))? {
         if let Event::Key(key) = event::read()? {
-            if key.kind == event::KeyEventKind::Press && key.code == KeyCode::Char('q') {
-                return Ok(true);
-            } else if key.code == KeyCode::Up || key.code == KeyCode::Char('k') && data.list_pos > 0
-            {
-                data.list_pos -= 1;
-            } else if key.code == KeyCode::Down
-                || key.code == KeyCode::Char('j') && data.list_pos < data.list.len() - 1
-            {
-                data.list_pos += 1;
-            } else if key.code == KeyCode::Enter {
-                let command = data.list[data.list_pos].command.clone();
-                match fork() {
-                    Ok(Fork::Parent(child)) => {
-                        println!(
-                            "Continuing execution in parent process, new child has pid: {}",
-                            child
-                        );
-                    }
-                    Ok(Fork::Child) => {
-                        Command::new("sh").arg("-c").arg(command).spawn().unwrap();
-                    }
-                    Err(_) => println!("Fork failed"),
+            if key.kind != event::KeyEventKind::Press {
+                return Ok(false);
+            }
+            if data.error.take().is_some() {
+                return Ok(false);
+            }
+            return match data.mode {
+                Mode::Normal => handle_normal_key(data, key),
+                Mode::Search => handle_search_key(data, key),
+                Mode::Errors => handle_errors_key(data, key),
+                Mode::Form => handle_form_key(data, key),
+                Mode::ConfirmDelete => handle_confirm_delete_key(data, key),
+                Mode::Help => {
+                    data.mode = Mode::Normal;
+                    Ok(false)
                 }
-                thread::sleep(Duration::from_millis(5000));
-                return Ok(true);
+                Mode::Tags => handle_tags_key(data, key),
+            };
+        }
+    }
+    Ok(false)
+}
+
+/// Looks up what `key` does given the keys already pressed towards a chord. A key that
+/// doesn't carry on the chord is looked at on its own.
+fn key_action(data: &mut GlobalInfo, key: KeyEvent) -> Option<Action> {
+    data.pending.push(key.into());
+    loop {
+        match data.settings.keymap.lookup(&data.pending) {
+            Lookup::Action(action) => {
+                data.pending.clear();
+                return Some(action);
+            }
+            Lookup::Pending => return None,
+            Lookup::None if data.pending.len() > 1 => {
+                data.pending.drain(..data.pending.len() - 1);
+            }
+            Lookup::None => {
+                data.pending.clear();
+                return None;
+            }
+        }
+    }
+}
+
+fn handle_normal_key(data: &mut GlobalInfo, key: KeyEvent) -> io::Result<bool> {
+    let Some(action) = key_action(data, key) else {
+        return Ok(false);
+    };
+    match action {
+        Action::Quit => return Ok(true),
+        Action::Up => move_selection(data, -1),
+        Action::Down => move_selection(data, 1),
+        Action::Top => move_selection(data, isize::MIN),
+        Action::Bottom => move_selection(data, isize::MAX),
+        Action::Collapse => set_collapsed(data, true),
+        Action::Expand => set_collapsed(data, false),
+        Action::Fold => toggle_category(data),
+        Action::Search => {
+            data.saved_pos = data.list_pos;
+            data.mode = Mode::Search;
+            data.query.clear();
+            update_search(data);
+        }
+        Action::Select => match data.selected_row() {
+            Some(Row::Category(_) | Row::Group(_)) => toggle_category(data),
+            _ => return launch_selected(data, data.stay_open),
+        },
+        Action::LaunchStayOpen => return launch_selected(data, true),
+        Action::Log => data.show_log = !data.show_log,
+        Action::Add => {
+            let category = match data.selected_row() {
+                Some(Row::Category(category)) => category,
+                Some(Row::Entry(index)) => data.list[index].category.clone(),
+                _ => String::new(),
+            };
+            open_form(data, Editing::New(category));
+        }
+        Action::Edit => {
+            if let Some(index) = editable_selection(data) {
+                let program = &data.list[index];
+                let editing = Editing::Existing {
+                    category: program.category.clone(),
+                    source: program.source.clone(),
+                };
+                open_form(data, editing);
+            }
+        }
+        Action::OpenEditor => {
+            if let Some(program) = data.selected() {
+                data.suspend = Some(Suspend::Edit(program.source.clone()));
+            }
+        }
+        Action::Delete => {
+            if editable_selection(data).is_some() {
+                data.mode = Mode::ConfirmDelete;
+            }
+        }
+        Action::Errors => {
+            data.errors_scroll = 0;
+            data.mode = Mode::Errors;
+        }
+        Action::Help => data.mode = Mode::Help,
+        Action::Pin => toggle_pin(data),
+        Action::Tags => {
+            data.tag_pos = 0;
+            data.mode = Mode::Tags;
+        }
+        Action::Sort => {
+            data.state.sort = Some(data.sort().next());
+            data.list_pos = 0;
+            if let Err(e) = state::save(&data.state) {
+                data.notify(format!("Could not save the sort order. {}", e));
             }
         }
+        Action::StayOpen => {
+            data.stay_open = !data.stay_open;
+            let state = if data.stay_open { "on" } else { "off" };
+            data.notify(format!("Stay open after launching: {}", state));
+        }
+    }
+    Ok(false)
+}
+
+/// Pins the selected entry to the top of the list, or unpins it. Kept in the state file
+/// so the entry's own file is never touched.
+fn toggle_pin(data: &mut GlobalInfo) {
+    let Some(program) = data.selected() else {
+        return;
+    };
+    let (id, title) = (program.id(), program.title.clone());
+    let row = data.selected_row();
+    let message = match data.state.pinned.iter().position(|pinned| *pinned == id) {
+        Some(i) => {
+            data.state.pinned.remove(i);
+            format!("Unpinned {}", title)
+        }
+        None => {
+            data.state.pinned.push(id.clone());
+            format!("Pinned {}", title)
+        }
+    };
+    match state::save(&data.state) {
+        Ok(()) => data.notify(message),
+        Err(e) => data.notify(format!("Could not save the pinned entries. {}", e)),
+    }
+    // Stay on the same row as the group above it grows or shrinks.
+    match data
+        .rows()
+        .iter()
+        .position(|other| Some(other) == row.as_ref())
+    {
+        Some(pos) => data.list_pos = pos,
+        None => select_id(data, Some(id)),
+    }
+}
+
+fn handle_tags_key(data: &mut GlobalInfo, key: KeyEvent) -> io::Result<bool> {
+    let tags = tags::all(&data.list);
+    let select = data.selected().map(Program::id);
+    match key.code {
+        KeyCode::Esc | KeyCode::Enter => data.mode = Mode::Normal,
+        KeyCode::Char(' ') => {
+            if let Some((tag, _)) = tags.get(data.tag_pos) {
+                data.tag_filter.toggle(tag);
+            }
+        }
+        KeyCode::Tab => {
+            data.tag_filter.mode = match data.tag_filter.mode {
+                tags::Match::Any => tags::Match::All,
+                tags::Match::All => tags::Match::Any,
+            }
+        }
+        KeyCode::Backspace => data.tag_filter.tags.clear(),
+        _ => match key_action(data, key) {
+            Some(Action::Up) => data.tag_pos = data.tag_pos.saturating_sub(1),
+            Some(Action::Down) => {
+                data.tag_pos = (data.tag_pos + 1).min(tags.len().saturating_sub(1))
+            }
+            Some(Action::Quit | Action::Tags) => data.mode = Mode::Normal,
+            _ => {}
+        },
+    }
+    // The list under the picker changes as tags are picked, keep the cursor on the same
+    // entry while it's still listed.
+    select_id(data, select);
+    Ok(false)
+}
+
+fn handle_errors_key(data: &mut GlobalInfo, key: KeyEvent) -> io::Result<bool> {
+    if key.code == KeyCode::Esc {
+        data.mode = Mode::Normal;
+        return Ok(false);
+    }
+    match key_action(data, key) {
+        Some(Action::Quit | Action::Errors) => data.mode = Mode::Normal,
+        Some(Action::Up) => data.errors_scroll = data.errors_scroll.saturating_sub(1),
+        Some(Action::Down) => data.errors_scroll += 1,
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// The selected entry if it has a file to itself. Entries that share a file through a
+/// `[[program]]` array are left alone so we never rewrite someone's hand kept file.
+fn editable_selection(data: &mut GlobalInfo) -> Option<usize> {
+    let index = data.selected_row()?.entry()?;
+    let program = &data.list[index];
+    if program.index.is_some() {
+        data.error = Some(format!(
+            "{} is one of several programs in {}, edit that file by hand.",
+            program.title,
+            program.source.display()
+        ));
+        return None;
+    }
+    Some(index)
+}
+
+fn open_form(data: &mut GlobalInfo, editing: Editing) {
+    let (title, program) = match &editing {
+        Editing::New(_) => ("New program".to_string(), None),
+        Editing::Existing { source, .. } => {
+            let program = data
+                .list
+                .iter()
+                .find(|p| p.source == *source)
+                .map(|p| p.original.as_deref().unwrap_or(p));
+            let title = program.map(|p| p.title.as_str()).unwrap_or_default();
+            (format!("Edit {}", title), program)
+        }
+    };
+    let value = |f: fn(&Program) -> String| program.map(f).unwrap_or_default();
+    data.form = Some(form::Form::new(
+        title,
+        vec![
+            form::Field::new("Title", value(|p| p.title.clone()), form::Kind::Line),
+            // Entries run without a shell get their arguments a line each.
+            match program.filter(|p| !p.args.is_empty()) {
+                Some(program) => form::Field::new(
+                    "Arguments (one per line)",
+                    program.args.join("\n"),
+                    form::Kind::Text,
+                ),
+                None => form::Field::new("Command", value(|p| p.command.clone()), form::Kind::Line),
+            },
+            form::Field::new(
+                "Description",
+                value(|p| p.description.clone()),
+                form::Kind::Text,
+            ),
+            form::Field::toggle("Run in terminal", program.is_some_and(|p| p.terminal)),
+            form::Field::new(
+                "Tags (comma separated)",
+                value(|p| p.tags.join(", ")),
+                form::Kind::Line,
+            ),
+            form::Field::new(
+                "Id (optional)",
+                value(|p| p.id.clone().unwrap_or_default()),
+                form::Kind::Line,
+            ),
+        ],
+    ));
+    data.editing = Some(editing);
+    data.mode = Mode::Form;
+}
+
+fn handle_form_key(data: &mut GlobalInfo, key: KeyEvent) -> io::Result<bool> {
+    let Some(form) = &mut data.form else {
+        data.mode = Mode::Normal;
+        return Ok(false);
+    };
+    match form.handle_key(key) {
+        form::Outcome::Continue => {}
+        form::Outcome::Cancel => close_form(data),
+        form::Outcome::Submit if data.launching.is_some() => return launch_with_answers(data),
+        form::Outcome::Submit => save_form(data),
+    }
+    Ok(false)
+}
+
+fn close_form(data: &mut GlobalInfo) {
+    data.form = None;
+    data.editing = None;
+    data.launching = None;
+    data.mode = Mode::Normal;
+}
+
+/// Validates the entry form and writes it out, leaving the form open with the problem
+/// shown if something's wrong.
+fn save_form(data: &mut GlobalInfo) {
+    let (Some(form), Some(editing), Some(config_path)) =
+        (&mut data.form, &data.editing, &data.config_path)
+    else {
+        return;
+    };
+    // Start from the entry being edited so whatever the form doesn't show is kept.
+    let mut program = match editing {
+        Editing::Existing { source, .. } => data
+            .list
+            .iter()
+            .find(|p| p.source == *source)
+            .map(|p| p.original.as_deref().unwrap_or(p)),
+        Editing::New(_) => None,
+    }
+    .cloned()
+    .unwrap_or_default();
+    let id = form.value("Id (optional)").trim().to_string();
+    program.id = Some(id).filter(|id| !id.is_empty());
+    program.title = form.value("Title").trim().to_string();
+    program.description = form.value("Description").to_string();
+    program.terminal = form.field_on("Run in terminal");
+    program.tags = form
+        .value("Tags (comma separated)")
+        .split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    if program.args.is_empty() {
+        program.command = form.value("Command").trim().to_string();
+    } else {
+        program.args = form
+            .value("Arguments (one per line)")
+            .lines()
+            .map(str::to_string)
+            .filter(|arg| !arg.is_empty())
+            .collect();
+    }
+    if let Err(e) = config::validate(&program) {
+        form.error = Some(e);
+        return;
+    }
+
+    let (category, path) = match editing {
+        Editing::New(category) => {
+            let dir = config_path.join(category);
+            (category.clone(), config::new_path(&dir, &program.title))
+        }
+        Editing::Existing { category, source } => (category.clone(), source.clone()),
+    };
+    program.category = category;
+    program.source = path.clone();
+    let id = program.id();
+    if let Some(other) = data.list.iter().find(|p| p.id() == id && p.source != path) {
+        form.error = Some(format!(
+            "{} in {} already has the id {}.",
+            other.title,
+            other.source.display(),
+            id
+        ));
+        return;
+    }
+    if let Err(e) = config::save(&program, &path) {
+        form.error = Some(format!("Could not save {}. {}", path.display(), e));
+        return;
+    }
+    data.notify(format!("Saved {} to {}", program.title, path.display()));
+    close_form(data);
+    reload_config(data, Some(program.id()));
+}
+
+fn handle_confirm_delete_key(data: &mut GlobalInfo, key: KeyEvent) -> io::Result<bool> {
+    data.mode = Mode::Normal;
+    if key.code != KeyCode::Char('y') {
+        return Ok(false);
+    }
+    let Some(program) = data.selected() else {
+        return Ok(false);
+    };
+    let (title, path) = (program.title.clone(), program.source.clone());
+    match fs::remove_file(&path) {
+        Ok(()) => {
+            data.notify(format!("Deleted {}", title));
+            reload_config(data, None);
+        }
+        Err(e) => data.error = Some(format!("Could not delete {}. {}", path.display(), e)),
+    }
+    Ok(false)
+}
+
+/// Loads a single config file again, swapping its entries in where the old ones were.
+/// If the file no longer parses at all the old entries stay so the problem can be shown
+/// next to them and fixed.
+fn reload_file(data: &mut GlobalInfo, path: &Path) {
+    let Some(config_path) = &data.config_path else {
+        return;
+    };
+    // Settings can change every entry through `[vars]`, so load it all again.
+    if path == config_path.join(settings::FILE) || path.starts_with(config_path.join(theme::DIR)) {
+        reload_config(data, None);
+        return;
+    }
+    let select = data.selected().map(Program::id);
+    let vars = vars::Vars::new(config_path, &data.settings.vars);
+    let (mut programs, mut errors) = config::load_file(config_path, path, &vars);
+    if !programs.is_empty() || errors.is_empty() {
+        // Which of two files sharing an id gets left out depends on the order everything
+        // is loaded in, and an id this file gave up may free one left out elsewhere.
+        let taken = programs.iter().any(|program| {
+            data.list
+                .iter()
+                .any(|other| other.source != path && other.id() == program.id())
+        });
+        let given_up = data
+            .list
+            .iter()
+            .filter(|program| program.source == path)
+            .any(|old| !programs.iter().any(|program| program.id() == old.id()));
+        if taken || given_up {
+            reload_config(data, select);
+            return;
+        }
+    }
+    errors.extend(config::drop_duplicates(&mut programs));
+    data.errors.retain(|error| error.path != path);
+    if programs.is_empty() && !errors.is_empty() {
+        data.notify(format!("{} has a problem", path.display()));
+    } else {
+        let at = data
+            .list
+            .iter()
+            .position(|program| program.source == path)
+            .unwrap_or(data.list.len());
+        data.list.retain(|program| program.source != path);
+        let at = at.min(data.list.len());
+        data.list.splice(at..at, programs);
+    }
+    data.errors.extend(errors);
+    if data.mode == Mode::Search {
+        data.hits = data.search();
+    }
+    select_id(data, select);
+}
+
+/// Loads the settings and the config directory again, keeping the cursor on the entry
+/// with id `select` or, failing that, on whichever entry was selected before. Stay open
+/// is left alone as it may have been toggled with `s` since.
+fn reload_config(data: &mut GlobalInfo, select: Option<String>) {
+    let Some(config_path) = &data.config_path else {
+        return;
+    };
+    let select = select.or_else(|| data.selected().map(Program::id));
+    let loaded = config::load_from(config_path.clone());
+    data.settings = loaded.settings;
+    data.list = loaded.programs;
+    data.errors = loaded.errors;
+    if data.mode == Mode::Search {
+        data.hits = data.search();
+    }
+    select_id(data, select);
+}
+
+/// Moves the cursor onto the entry with id `select`, or keeps it where it is if that's
+/// gone.
+fn select_id(data: &mut GlobalInfo, select: Option<String>) {
+    let rows = data.rows();
+    let found = select.and_then(|id| {
+        rows.iter()
+            .position(|row| row.entry().is_some_and(|i| data.list[i].id() == id))
+    });
+    data.list_pos = found.unwrap_or(data.list_pos.min(rows.len().saturating_sub(1)));
+}
+
+fn handle_search_key(data: &mut GlobalInfo, key: KeyEvent) -> io::Result<bool> {
+    match key.code {
+        KeyCode::Esc => {
+            data.mode = Mode::Normal;
+            data.query.clear();
+            data.hits.clear();
+            data.list_pos = data.saved_pos;
+        }
+        KeyCode::Enter => return launch_selected(data, data.stay_open),
+        KeyCode::Up => move_selection(data, -1),
+        KeyCode::Down => move_selection(data, 1),
+        KeyCode::Backspace => {
+            data.query.pop();
+            update_search(data);
+        }
+        KeyCode::Char(c) => {
+            data.query.push(c);
+            update_search(data);
+        }
+        _ => {}
+    }
+    Ok(false)
+}
+
+fn move_selection(data: &mut GlobalInfo, by: isize) {
+    let len = data.rows().len();
+    if len == 0 {
+        return;
+    }
+    data.list_pos = data.list_pos.saturating_add_signed(by).min(len - 1);
+}
+
+fn toggle_category(data: &mut GlobalInfo) {
+    let collapse = match data.selected_row() {
+        Some(Row::Category(category)) => !data.collapsed.contains(&category),
+        Some(Row::Group(group)) => !data.collapsed_groups.contains(&group),
+        _ => return,
+    };
+    set_collapsed(data, collapse);
+}
+
+/// Collapses or expands the category or group under the cursor. Collapsing from an
+/// entry folds the section it's listed in and moves the cursor onto its header.
+fn set_collapsed(data: &mut GlobalInfo, collapse: bool) {
+    let header = match data.selected_row() {
+        Some(Row::Entry(index)) if collapse => Row::Category(data.list[index].category.clone()),
+        Some(Row::GroupEntry(group, _)) if collapse => Row::Group(group),
+        Some(row @ (Row::Category(_) | Row::Group(_))) => row,
+        _ => return,
+    };
+    match &header {
+        Row::Category(category) if category.is_empty() => return,
+        Row::Category(category) if collapse => {
+            data.collapsed.insert(category.clone());
+        }
+        Row::Category(category) => {
+            data.collapsed.remove(category);
+        }
+        Row::Group(group) if collapse => {
+            data.collapsed_groups.insert(*group);
+        }
+        Row::Group(group) => {
+            data.collapsed_groups.remove(group);
+        }
+        _ => return,
+    }
+    if let Some(pos) = data.rows().iter().position(|row| *row == header) {
+        data.list_pos = pos;
     }
+}
+
+/// Re-ranks the list against the current query and jumps back to the top hit.
+fn update_search(data: &mut GlobalInfo) {
+    data.hits = data.search();
+    data.list_pos = 0;
+}
+
+/// Launches the selected entry, asking for its params first if it has any. Returns
+/// whether the launcher should now quit, which it does after a successful launch unless
+/// `stay_open` is set.
+fn launch_selected(data: &mut GlobalInfo, stay_open: bool) -> io::Result<bool> {
+    let Some(index) = data.selected_row().and_then(|row| row.entry()) else {
+        return Ok(false);
+    };
+    let program = &data.list[index];
+    if !program.params.is_empty() {
+        open_params_form(data, index, stay_open);
+        return Ok(false);
+    }
+    let job = launch::Job::new(program, &data.settings);
+    launch(data, job, program.terminal, stay_open)
+}
+
+fn launch(
+    data: &mut GlobalInfo,
+    job: launch::Job,
+    terminal: bool,
+    stay_open: bool,
+) -> io::Result<bool> {
+    if terminal {
+        data.suspend = Some(Suspend::Run(Box::new(job)));
+        return Ok(false);
+    }
+    if job.pre_launch.is_empty() {
+        let result = launch::spawn(&job);
+        return Ok(launched(data, &job.title, stay_open, result));
+    }
+    // The supervisor only reports back once the hooks are done, which can take a while
+    // if they're mounting drives, so wait for it off to the side.
+    let title = job.title.clone();
+    let (send, result) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = send.send(launch::spawn(&job));
+    });
+    data.starting.push(Starting {
+        title,
+        stay_open,
+        result,
+    });
     Ok(false)
 }
+
+/// Reports how starting `title` went. Returns whether the launcher should now quit.
+fn launched(data: &mut GlobalInfo, title: &str, stay_open: bool, result: io::Result<u32>) -> bool {
+    match result {
+        Ok(pid) if stay_open => {
+            data.notify(format!("Started {} (pid {})", title, pid));
+            false
+        }
+        Ok(_) => true,
+        Err(e) => {
+            data.error = Some(format!("Could not start {}. {}", title, e));
+            false
+        }
+    }
+}
+
+/// Picks up launches that were waiting on their `pre_launch` hooks and have now
+/// started, or failed to. Returns whether the launcher should now quit.
+fn check_starting(data: &mut GlobalInfo) -> bool {
+    let mut quit = false;
+    for starting in std::mem::take(&mut data.starting) {
+        match starting.result.try_recv() {
+            Ok(result) => quit |= launched(data, &starting.title, starting.stay_open, result),
+            Err(mpsc::TryRecvError::Empty) => data.starting.push(starting),
+            Err(mpsc::TryRecvError::Disconnected) => {}
+        }
+    }
+    quit
+}
+
+/// Asks for the params of the entry at `index`, each starting at its default.
+fn open_params_form(data: &mut GlobalInfo, index: usize, stay_open: bool) {
+    let program = &data.list[index];
+    let fields = program
+        .params
+        .iter()
+        .map(|param| {
+            let kind = if param.choices.is_empty() {
+                form::Kind::Line
+            } else {
+                form::Kind::Choice(param.choices.clone())
+            };
+            let value = match &param.default {
+                Some(default) => default.clone(),
+                None => param.choices.first().cloned().unwrap_or_default(),
+            };
+            form::Field::new(param.label(), value, kind)
+        })
+        .collect();
+    let mut form = form::Form::new(format!("Launch {}", program.title), fields);
+    form.submit = "launch";
+    data.form = Some(form);
+    data.launching = Some(Launching {
+        source: program.source.clone(),
+        index: program.index,
+        stay_open,
+    });
+    data.mode = Mode::Form;
+}
+
+/// Fills the answers in the params form into the entry and launches it, leaving the
+/// form open with the problem shown if something's wrong.
+fn launch_with_answers(data: &mut GlobalInfo) -> io::Result<bool> {
+    let (Some(form), Some(launching)) = (&mut data.form, &data.launching) else {
+        return Ok(false);
+    };
+    let found = data
+        .list
+        .iter()
+        .find(|p| p.source == launching.source && p.index == launching.index);
+    let Some(program) = found else {
+        form.error = Some("The entry is gone, it was removed from the config.".to_string());
+        return Ok(false);
+    };
+    let answers = program
+        .params
+        .iter()
+        .zip(&form.fields)
+        .map(|(param, field)| (param.name.clone(), field.value.trim().to_string()))
+        .collect();
+    let mut program = program.clone();
+    if let Err(e) = vars::fill_params(&mut program, &answers) {
+        form.error = Some(format!("{}.", e));
+        return Ok(false);
+    }
+    let job = launch::Job::new(&program, &data.settings);
+    let stay_open = launching.stay_open;
+    close_form(data);
+    launch(data, job, program.terminal, stay_open)
+}
+
 fn ui(frame: &mut Frame, data: &mut GlobalInfo) {
+    // Cloned so `data` can still be handed on mutably below.
+    let theme = data.settings.style.clone();
+    frame.render_widget(Block::new().style(theme.text), frame.size());
     let main_layout = Layout::new(
         Direction::Vertical,
         [
             Constraint::Length(1),
             Constraint::Min(0),
             Constraint::Length(3),
+            Constraint::Length(1),
         ],
     )
     .split(frame.size());
+    let mut title = format!("GLauncher · sorted by {}", data.sort().name());
+    if data.tag_filter.is_active() {
+        title.push_str(&format!(" · tags {}", data.tag_filter.describe()));
+    }
+    if data.stay_open {
+        title.push_str(" [stay open]");
+    }
+    if !data.starting.is_empty() {
+        let titles: Vec<&str> = data.starting.iter().map(|s| s.title.as_str()).collect();
+        title.push_str(&format!(" · running pre_launch for {}…", titles.join(", ")));
+    }
     frame.render_widget(
-        Block::new().borders(Borders::TOP).title("GLauncher"),
+        Block::new()
+            .borders(Borders::TOP)
+            .border_set(theme.border)
+            .border_style(theme.border_style)
+            .title(title),
         main_layout[0],
     );
 
+    if data.list.is_empty() {
+        render_onboarding(frame, data, main_layout[1].union(main_layout[2]));
+    } else {
+        render_entries(frame, data, main_layout[1], main_layout[2]);
+    }
+
+    if let Some((_, posted)) = &data.toast {
+        if posted.elapsed() > TOAST_DURATION {
+            data.toast = None;
+        }
+    }
+    if let Some((message, _)) = &data.toast {
+        frame.render_widget(Paragraph::new(message.clone()), main_layout[3]);
+    } else if !data.errors.is_empty() {
+        frame.render_widget(
+            Paragraph::new(format!(
+                "{} config problem(s), press {} to see them",
+                data.errors.len(),
+                data.settings.keymap.hint(Action::Errors)
+            ))
+            .style(theme.warning),
+            main_layout[3],
+        );
+    }
+
+    match data.mode {
+        Mode::Errors => render_errors(frame, data),
+        Mode::Help => render_help(frame, data),
+        Mode::Tags => tags::render(frame, &data.tag_filter, &data.list, data.tag_pos, &theme),
+        Mode::Form => {
+            if let Some(form) = &data.form {
+                form.render(frame, frame.size(), &theme);
+            }
+        }
+        Mode::ConfirmDelete => {
+            if let Some(program) = data.selected() {
+                let area = centered_rect(60, 3, frame.size());
+                frame.render_widget(Clear, area);
+                frame.render_widget(
+                    Paragraph::new(format!(
+                        "Delete {} ({})? y/n",
+                        program.title,
+                        program.source.display()
+                    ))
+                    .block(theme.block().border_style(theme.error)),
+                    area,
+                );
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(error) = &data.error {
+        let area = centered_rect(60, 5, frame.size());
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(error.clone())
+                .wrap(Wrap { trim: true })
+                .block(theme.block().title("Error").border_style(theme.error)),
+            area,
+        );
+    }
+}
+
+/// The list, detail pane and command box.
+fn render_entries(frame: &mut Frame, data: &mut GlobalInfo, area: Rect, command_area: Rect) {
+    let theme = &data.settings.style;
     let inner_layout = Layout::new(
         Direction::Horizontal,
         [Constraint::Percentage(50), Constraint::Percentage(50)],
     )
-    .split(main_layout[1]);
+    .split(area);
+    let left_layout = if data.mode == Mode::Search {
+        Layout::new(
+            Direction::Vertical,
+            [Constraint::Min(0), Constraint::Length(3)],
+        )
+        .split(inner_layout[0])
+    } else {
+        Layout::new(Direction::Vertical, [Constraint::Min(0)]).split(inner_layout[0])
+    };
+
     let mut items = Vec::new();
-    for item in &data.list {
-        items.push(item.title.clone())
+    match data.mode {
+        Mode::Normal
+        | Mode::Errors
+        | Mode::Form
+        | Mode::ConfirmDelete
+        | Mode::Help
+        | Mode::Tags => {
+            for row in data.rows() {
+                items.push(row_line(data, &row))
+            }
+        }
+        Mode::Search => {
+            for hit in &data.hits {
+                items.push(highlight(
+                    &data.list[hit.index].title,
+                    &hit.title_matches,
+                    theme.matched,
+                ))
+            }
+        }
     }
 
     let list = List::new(items)
-        .block(
-            Block::new()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded),
-        )
-        .highlight_symbol(">>");
+        .block(theme.block())
+        .highlight_style(theme.selected)
+        .highlight_symbol(&theme.highlight_symbol);
 
     data.liststate.select(Some(data.list_pos));
-    frame.render_stateful_widget(list, inner_layout[0], &mut data.liststate);
+    frame.render_stateful_widget(list, left_layout[0], &mut data.liststate);
 
+    if data.mode == Mode::Search {
+        frame.render_widget(
+            Paragraph::new(format!("/{}", data.query)).block(theme.block().title(format!(
+                "Search ({}/{})",
+                data.hits.len(),
+                data.list.len()
+            ))),
+            left_layout[1],
+        );
+    }
+
+    let command_title = match data.selected().and_then(Program::working_dir) {
+        Some(dir) => format!("Command (in {})", dir.display()),
+        None => "Command".to_string(),
+    };
+    let (title, description, command) = match data.selected() {
+        Some(program) => (
+            program.title.clone(),
+            program.description.clone(),
+            program.command_line(),
+        ),
+        None => Default::default(),
+    };
+
+    // Problems with the file the selected entry came from, e.g. after a bad edit.
+    let problems: Vec<Line> = data
+        .selected()
+        .map(|program| {
+            data.errors
+                .iter()
+                .filter(|error| error.path == program.source)
+                .map(|error| Line::from(error.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
     let right_layout = Layout::new(
         Direction::Vertical,
-        [Constraint::Length(3), Constraint::Min(0)],
+        [
+            Constraint::Length(3),
+            Constraint::Length(if problems.is_empty() {
+                0
+            } else {
+                problems.len() as u16 + 2
+            }),
+            Constraint::Min(0),
+            Constraint::Length(3),
+        ],
     )
     .split(inner_layout[1]);
+    // The title followed by the entry's tags as chips.
+    let mut title = vec![Span::raw(title)];
+    for tag in data
+        .selected()
+        .map(|program| &program.tags)
+        .into_iter()
+        .flatten()
+    {
+        title.push(Span::raw(" "));
+        title.push(tags::chip(tag, theme));
+    }
     frame.render_widget(
-        Paragraph::new(data.list[data.list_pos].title.clone()).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded),
-        ),
+        Paragraph::new(Line::from(title)).block(theme.block()),
         right_layout[0],
     );
-    frame.render_widget(
-        Paragraph::new(data.list[data.list_pos].description.clone()).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded),
+    if !problems.is_empty() {
+        frame.render_widget(
+            Paragraph::new(problems).wrap(Wrap { trim: false }).block(
+                theme
+                    .block()
+                    .title("Problem in file")
+                    .border_style(theme.error),
+            ),
+            right_layout[1],
+        );
+    }
+    if data.show_log {
+        render_log(frame, data, right_layout[2]);
+    } else {
+        frame.render_widget(
+            Paragraph::new(description).block(theme.block()),
+            right_layout[2],
+        );
+    }
+    let stats = match data
+        .selected()
+        .and_then(|program| data.stats.get(&program.id()))
+    {
+        Some(stats) => format!(
+            "Last played {} · {} total · {} launches",
+            clock::format(stats.last),
+            clock::format_duration(stats.total),
+            stats.launches
         ),
-        right_layout[1],
+        None => "Never launched".to_string(),
+    };
+    frame.render_widget(
+        Paragraph::new(stats).block(theme.block().title("Played")),
+        right_layout[3],
     );
     frame.render_widget(
-        Paragraph::new(data.list[data.list_pos].command.clone()).block(
-            Block::default()
-                .title("Command")
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded),
-        ),
-        main_layout[2],
+        Paragraph::new(command).block(theme.block().title(command_title)),
+        command_area,
+    );
+}
+
+/// Shown instead of the list when there's nothing to launch yet.
+fn render_onboarding(frame: &mut Frame, data: &GlobalInfo, area: Rect) {
+    let theme = &data.settings.style;
+    let config_path = data
+        .config_path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "~/.config/glauncher".to_string());
+    let text = format!(
+        "No programs to launch yet.
+
+To add one make a file ending in .toml in {}, for example steam.toml:
+
+title = \"Steam\"
+command = \"steam\"
+description = \"\"\"
+Games.
+\"\"\"
+
+Folders in there become categories. Press {} to quit.",
+        config_path,
+        data.settings.keymap.hint(Action::Quit)
+    );
+    frame.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .block(theme.block().title("Welcome to GLauncher")),
+        area,
+    );
+}
+
+/// Every file or entry that failed to load, with where and why.
+fn render_errors(frame: &mut Frame, data: &GlobalInfo) {
+    let theme = &data.settings.style;
+    let lines: Vec<Line> = data
+        .errors
+        .iter()
+        .map(|error| Line::from(error.to_string()))
+        .collect();
+    let size = frame.size();
+    let height = (lines.len() as u16 + 2).clamp(5, size.height.saturating_sub(4).max(5));
+    let area = centered_rect(80, height, size);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((data.errors_scroll, 0))
+            .block(
+                theme
+                    .block()
+                    .title("Config problems (Esc to close)")
+                    .border_style(theme.warning),
+            ),
+        area,
+    );
+}
+
+/// Every action and the keys bound to it, straight from the keymap.
+fn render_help(frame: &mut Frame, data: &GlobalInfo) {
+    let theme = &data.settings.style;
+    let keymap = &data.settings.keymap;
+    let lines: Vec<Line> = keys::Action::ALL
+        .into_iter()
+        .map(|action| {
+            let chords: Vec<String> = keymap
+                .chords(action)
+                .iter()
+                .map(|chord| chord.to_string())
+                .collect();
+            Line::from(vec![
+                Span::styled(format!("{:>16}  ", chords.join(", ")), theme.key),
+                Span::raw(action.description()),
+            ])
+        })
+        .collect();
+    let area = centered_rect(70, lines.len() as u16 + 2, frame.size());
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(theme.block().title("Keys (any key to close)")),
+        area,
+    );
+}
+
+/// A rect `percent_x` wide and `height` tall in the middle of `area`.
+fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    let vertical = Layout::new(
+        Direction::Vertical,
+        [
+            Constraint::Fill(1),
+            Constraint::Length(height),
+            Constraint::Fill(1),
+        ],
     )
+    .split(area);
+    Layout::new(
+        Direction::Horizontal,
+        [
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ],
+    )
+    .split(vertical[1])[1]
 }
 
-fn handle_setup() -> GlobalInfo {
-    let mut data = GlobalInfo::default();
-    let config_path = dirs::config_dir().unwrap().join("glauncher");
+/// The end of the selected entry's most recent log, kept scrolled to the bottom.
+fn render_log(frame: &mut Frame, data: &GlobalInfo, area: Rect) {
+    let theme = &data.settings.style;
+    let latest = data
+        .selected()
+        .and_then(|program| logs::latest(&program.id()));
+    let (title, text) = match &latest {
+        Some(path) => {
+            let started = logs::started(path).map(clock::format).unwrap_or_default();
+            let text = logs::tail(path).unwrap_or_else(|e| format!("Could not read log. {}", e));
+            (format!("Log ({})", started), text)
+        }
+        None => ("Log".to_string(), "No runs logged yet.".to_string()),
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    let height = area.height.saturating_sub(2) as usize;
+    let shown = lines[lines.len().saturating_sub(height)..].join("\n");
+    frame.render_widget(
+        Paragraph::new(shown).block(theme.block().title(title)),
+        area,
+    );
+}
 
-    if !config_path.exists() {
-        if let Err(e) = fs::create_dir_all(config_path.clone()) {
-            eprintln!("Could not make config directory. {}", e)
-        };
+fn row_line(data: &GlobalInfo, row: &Row) -> Line<'static> {
+    match row {
+        Row::Group(group) => {
+            let count = data.group_entries(*group).len();
+            let arrow = if data.collapsed_groups.contains(group) {
+                "▸"
+            } else {
+                "▾"
+            };
+            Line::from(format!("{} {} ({})", arrow, group.name(), count))
+                .style(data.settings.style.header)
+        }
+        Row::GroupEntry(_, index) => Line::from(format!("  {}", data.list[*index].title)),
+        Row::Category(category) => {
+            let depth = category.matches('/').count();
+            let name = category.rsplit('/').next().unwrap_or_default();
+            let count = data
+                .list
+                .iter()
+                .filter(|program| {
+                    program.category == *category
+                        || program.category.starts_with(&format!("{}/", category))
+                })
+                .count();
+            let arrow = if data.collapsed.contains(category) {
+                "▸"
+            } else {
+                "▾"
+            };
+            Line::from(format!(
+                "{}{} {}/ ({})",
+                "  ".repeat(depth),
+                arrow,
+                name,
+                count
+            ))
+            .style(data.settings.style.header)
+        }
+        Row::Entry(index) => {
+            let program = &data.list[*index];
+            let depth = if program.category.is_empty() {
+                0
+            } else {
+                program.category.matches('/').count() + 1
+            };
+            Line::from(format!("{}{}", "  ".repeat(depth), program.title))
+        }
     }
-    data.config_path = Some(config_path);
-    for file in fs::read_dir(data.config_path.as_ref().unwrap()).unwrap() {
-        let contents = read_to_string(file.unwrap().path());
-        match toml::from_str::<Program>(contents.unwrap().as_str()) {
-            Ok(entry) => data.list.push(entry),
-            Err(_) => {
-                eprintln!("Could not load config file as it is invalid.")
+}
+
+/// Renders `text` with the chars at `matches` picked out.
+fn highlight(text: &str, matches: &[usize], style: Style) -> Line<'static> {
+    let spans: Vec<Span> = text
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if matches.contains(&i) {
+                Span::styled(c.to_string(), style)
+            } else {
+                Span::raw(c.to_string())
             }
+        })
+        .collect();
+    Line::from(spans)
+}
+
+fn handle_setup() -> GlobalInfo {
+    let mut data = GlobalInfo {
+        state: state::load(),
+        ..Default::default()
+    };
+    // Runs that finished were written twice, once when they started. Only the last line
+    // is needed, so tidy up before loading.
+    let _ = history::compact();
+    data.reload_history();
+    let loaded = match config::load() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            data.errors.push(e);
+            data.mode = Mode::Errors;
+            return data;
         }
+    };
+    data.config_path = Some(loaded.path);
+    data.settings = loaded.settings;
+    data.list = loaded.programs;
+    data.errors.extend(loaded.errors);
+    if !data.errors.is_empty() {
+        data.mode = Mode::Errors;
     }
     data
 }
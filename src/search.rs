@@ -0,0 +1,104 @@
+use crate::config::Program;
+
+// Scoring weights for the fuzzy matcher. Consecutive runs and matches at the start of
+// words are rewarded so that "ff" ranks "Firefox" above "Steam Dev Tools: buffer".
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 24;
+const WORD_START: i64 = 20;
+const FIRST_CHAR: i64 = 12;
+const GAP: i64 = 1;
+
+pub struct Hit {
+    pub index: usize,
+    pub score: i64,
+    /// Char positions in the title that matched the query, used for highlighting.
+    pub title_matches: Vec<usize>,
+}
+
+/// Case insensitive subsequence match of `pattern` against `text`.
+/// Returns the score and the char positions of the matched characters.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    if pattern.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let text: Vec<char> = text.chars().collect();
+
+    // Try every position the first pattern char occurs at and keep the best scoring
+    // greedy match, so "sm" prefers "Steam Music" over "sysmon" style partial runs.
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in 0..text.len() {
+        if !eq(text[start], pattern[0]) {
+            continue;
+        }
+        if let Some(found) = match_from(&pattern, &text, start) {
+            if best.as_ref().is_none_or(|b| found.0 > b.0) {
+                best = Some(found);
+            }
+        }
+    }
+    best
+}
+
+fn match_from(pattern: &[char], text: &[char], start: usize) -> Option<(i64, Vec<usize>)> {
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut score = 0;
+    let mut p = 0;
+    for (i, c) in text.iter().enumerate().skip(start) {
+        if p == pattern.len() {
+            break;
+        }
+        if !eq(*c, pattern[p]) {
+            continue;
+        }
+        score += MATCH;
+        if i == 0 {
+            score += FIRST_CHAR;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += WORD_START;
+        }
+        match positions.last() {
+            Some(&last) if last + 1 == i => score += CONSECUTIVE,
+            Some(&last) => score -= GAP * (i - last - 1) as i64,
+            None => score -= GAP * i as i64,
+        }
+        positions.push(i);
+        p += 1;
+    }
+    if p == pattern.len() {
+        Some((score, positions))
+    } else {
+        None
+    }
+}
+
+fn eq(text: char, pattern: char) -> bool {
+    text == pattern || text.to_lowercase().eq(pattern.to_lowercase())
+}
+
+/// Filters and ranks `list` by `query`, matching against the title, description and
+/// command. Title matches count double as that's what the user is looking at.
+pub fn filter(list: &[Program], query: &str) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for (index, program) in list.iter().enumerate() {
+        let title = fuzzy_match(query, &program.title);
+        let description = fuzzy_match(query, &program.description).map(|(s, _)| s);
+        let command = fuzzy_match(query, &program.command_line()).map(|(s, _)| s);
+
+        let score = [title.as_ref().map(|(s, _)| s * 2), description, command]
+            .into_iter()
+            .flatten()
+            .max();
+
+        if let Some(score) = score {
+            hits.push(Hit {
+                index,
+                score,
+                title_matches: title.map(|(_, m)| m).unwrap_or_default(),
+            });
+        }
+    }
+    hits.sort_by(|a, b| b.score.cmp(&a.score).then(a.index.cmp(&b.index)));
+    hits
+}
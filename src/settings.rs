@@ -0,0 +1,108 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::LoadError,
+    keys::Keymap,
+    sort::Sort,
+    theme::{self, Theme},
+};
+
+/// Name of the settings file in the config directory. It's never loaded as a program.
+pub const FILE: &str = "settings.toml";
+
+/// App wide settings, read from `settings.toml` in the config directory. Everything is
+/// optional, see `Default` for what's used when it's left out.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Sort order used until another one is picked with Tab.
+    pub sort: Sort,
+    /// Keep running after launching something, like `--stay-open`.
+    pub stay_open: bool,
+    /// Shell that commands are run with as `<shell> -c <command>`.
+    pub shell: String,
+    /// Name of a built in theme or of a file in `themes/`.
+    pub theme: String,
+    /// Variables for entries to use as `${name}`, e.g. paths that differ between machines.
+    pub vars: BTreeMap<String, String>,
+    /// Commands run before every entry that doesn't have its own `pre_launch`.
+    pub pre_launch: Vec<String>,
+    /// Commands run after every entry that doesn't have its own `post_exit`.
+    pub post_exit: Vec<String>,
+    #[serde(skip)]
+    pub style: Theme,
+    /// Key bindings as written in the file, action name to a chord or list of chords.
+    pub keys: toml::Table,
+    #[serde(skip)]
+    pub keymap: Keymap,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            sort: Sort::File,
+            stay_open: false,
+            shell: "sh".to_string(),
+            theme: theme::BUILT_IN[0].to_string(),
+            vars: BTreeMap::new(),
+            pre_launch: Vec::new(),
+            post_exit: Vec::new(),
+            style: Theme::default(),
+            keys: toml::Table::new(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+/// Top level keys `Settings` understands, anything else gets a warning.
+const KNOWN: &[&str] = &[
+    "sort",
+    "stay_open",
+    "shell",
+    "theme",
+    "vars",
+    "pre_launch",
+    "post_exit",
+    "keys",
+];
+
+/// Reads the settings file in `config_path`. A missing file just means the defaults.
+/// Problems, including keys we don't know, come back alongside whatever could be used.
+pub fn load(config_path: &Path) -> (Settings, Vec<LoadError>) {
+    let path = config_path.join(FILE);
+    let (mut settings, mut errors) = read(&path);
+    let (keymap, key_errors) = Keymap::new(&settings.keys);
+    settings.keymap = keymap;
+    errors.extend(key_errors.into_iter().map(|e| LoadError::new(&path, e)));
+    let (style, theme_errors) = theme::load(config_path, &settings.theme);
+    settings.style = style;
+    errors.extend(theme_errors);
+    (settings, errors)
+}
+
+fn read(path: &Path) -> (Settings, Vec<LoadError>) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return (Settings::default(), Vec::new()),
+        Err(e) => return (Settings::default(), vec![LoadError::new(path, e)]),
+    };
+    let settings = match toml::from_str::<Settings>(&contents) {
+        Ok(settings) => settings,
+        Err(e) => {
+            let error = LoadError::from_toml(path, &contents, &e);
+            return (Settings::default(), vec![error]);
+        }
+    };
+    let mut errors = Vec::new();
+    if let Ok(table) = toml::from_str::<toml::Table>(&contents) {
+        for key in table.keys().filter(|key| !KNOWN.contains(&key.as_str())) {
+            errors.push(LoadError::new(
+                path,
+                format!("unknown setting `{}`, it's been ignored", key),
+            ));
+        }
+    }
+    (settings, errors)
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Program, history::Stats};
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sort {
+    /// The order the files are in on disk.
+    #[default]
+    File,
+    Title,
+    MostUsed,
+    Recent,
+    Frecency,
+}
+
+impl Sort {
+    pub fn next(self) -> Sort {
+        match self {
+            Sort::File => Sort::Title,
+            Sort::Title => Sort::MostUsed,
+            Sort::MostUsed => Sort::Recent,
+            Sort::Recent => Sort::Frecency,
+            Sort::Frecency => Sort::File,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sort::File => "file order",
+            Sort::Title => "title",
+            Sort::MostUsed => "most used",
+            Sort::Recent => "recently used",
+            Sort::Frecency => "frecency",
+        }
+    }
+}
+
+/// Indexes into `list` in the order given by `sort`. Ties keep file order.
+pub fn sorted(list: &[Program], stats: &HashMap<String, Stats>, sort: Sort) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..list.len()).collect();
+    let ids: Vec<String> = list.iter().map(Program::id).collect();
+    let stat = |i: usize| stats.get(&ids[i]);
+    match sort {
+        Sort::File => {}
+        Sort::Title => order.sort_by_cached_key(|&i| list[i].title.to_lowercase()),
+        Sort::MostUsed => {
+            order.sort_by_key(|&i| std::cmp::Reverse(stat(i).map_or(0, |s| s.launches)))
+        }
+        Sort::Recent => order.sort_by_key(|&i| std::cmp::Reverse(stat(i).map_or(0, |s| s.last))),
+        Sort::Frecency => {
+            order.sort_by_key(|&i| std::cmp::Reverse(stat(i).map_or(0, |s| s.frecency)))
+        }
+    }
+    order
+}
@@ -0,0 +1,36 @@
+use std::{fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{logs, sort::Sort};
+
+/// Things the launcher remembers between runs. Kept in the data dir, away from the
+/// hand written config files.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct State {
+    /// Last sort order picked with Tab, over the one in the settings.
+    pub sort: Option<Sort>,
+    /// Ids of the entries pinned to the top of the list, in the order they were pinned.
+    pub pinned: Vec<String>,
+}
+
+fn path() -> Option<PathBuf> {
+    logs::data_dir().map(|dir| dir.join("state.toml"))
+}
+
+pub fn load() -> State {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(state: &State) -> io::Result<()> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data dir"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = toml::to_string(state).map_err(io::Error::other)?;
+    fs::write(path, contents)
+}
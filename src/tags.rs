@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{config::Program, theme::Theme};
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Match {
+    /// Entries with at least one of the picked tags.
+    #[default]
+    Any,
+    /// Entries with every picked tag.
+    All,
+}
+
+/// The tags picked to narrow the list down with. Empty shows everything.
+#[derive(Default)]
+pub struct Filter {
+    pub tags: Vec<String>,
+    pub mode: Match,
+}
+
+impl Filter {
+    pub fn is_active(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    pub fn matches(&self, program: &Program) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let has = |tag: &String| program.tags.contains(tag);
+        match self.mode {
+            Match::Any => self.tags.iter().any(has),
+            Match::All => self.tags.iter().all(has),
+        }
+    }
+
+    pub fn toggle(&mut self, tag: &str) {
+        match self.tags.iter().position(|t| t == tag) {
+            Some(i) => {
+                self.tags.remove(i);
+            }
+            None => self.tags.push(tag.to_string()),
+        }
+    }
+
+    /// Short description for the title bar, e.g. "game + vr" or "game | vr".
+    pub fn describe(&self) -> String {
+        let joiner = match self.mode {
+            Match::Any => " | ",
+            Match::All => " + ",
+        };
+        self.tags.join(joiner)
+    }
+}
+
+/// Every tag used in `list` with how many entries have it, sorted by name.
+pub fn all(list: &[Program]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for tag in list.iter().flat_map(|program| &program.tags) {
+        *counts.entry(tag).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(tag, count)| (tag.to_string(), count))
+        .collect()
+}
+
+/// A tag drawn as a coloured chip. The colour is picked from the theme by the tag's
+/// name so it stays the same everywhere.
+pub fn chip(tag: &str, theme: &Theme) -> Span<'static> {
+    let sum = tag.bytes().map(usize::from).sum::<usize>();
+    let style = theme
+        .tags
+        .get(sum % theme.tags.len().max(1))
+        .copied()
+        .unwrap_or_default();
+    Span::styled(format!(" {} ", tag), style)
+}
+
+/// Popup to pick which tags the list is narrowed to.
+pub fn render(frame: &mut Frame, filter: &Filter, list: &[Program], pos: usize, theme: &Theme) {
+    let tags = all(list);
+    let lines: Vec<Line> = if tags.is_empty() {
+        vec![Line::from(
+            "No entries have tags yet, add some with tags = [\"...\"].",
+        )]
+    } else {
+        tags.iter()
+            .map(|(tag, count)| {
+                let picked = if filter.tags.contains(tag) {
+                    "[x]"
+                } else {
+                    "[ ]"
+                };
+                Line::from(vec![
+                    Span::raw(format!("{} ", picked)),
+                    chip(tag, theme),
+                    Span::raw(format!(" ({})", count)),
+                ])
+            })
+            .collect()
+    };
+    let mode = match filter.mode {
+        Match::Any => "any",
+        Match::All => "all",
+    };
+    let area = crate::centered_rect(50, lines.len() as u16 + 2, frame.size());
+    frame.render_widget(Clear, area);
+    let mut state = ListState::default().with_selected(Some(pos));
+    frame.render_stateful_widget(
+        List::new(lines)
+            .block(
+                theme
+                    .block()
+                    .title(format!("Tags, showing entries with {} of them", mode))
+                    .title_bottom("Space pick · Tab any/all · Backspace clear · Enter close"),
+            )
+            .highlight_style(theme.selected)
+            .highlight_symbol(&theme.highlight_symbol),
+        area,
+        &mut state,
+    );
+}
@@ -0,0 +1,335 @@
+use std::{env, fs, path::Path, str::FromStr};
+
+use ratatui::{prelude::*, symbols::border, widgets::*};
+
+use crate::config::LoadError;
+
+/// Folder in the config directory user themes are read from, `themes/<name>.toml`.
+/// It's never loaded for programs.
+pub const DIR: &str = "themes";
+
+/// Themes that come with GLauncher, the first one is the default.
+pub const BUILT_IN: [&str; 4] = ["dark", "light", "high-contrast", "no-colour"];
+
+/// Plain borders drawn with ASCII only, for consoles without box drawing characters.
+const ASCII: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// How everything is drawn.
+#[derive(Clone)]
+pub struct Theme {
+    pub border: border::Set,
+    /// Shown in front of the selected row in the list.
+    pub highlight_symbol: String,
+    /// Everything not covered below.
+    pub text: Style,
+    pub border_style: Style,
+    /// The selected row in the list.
+    pub selected: Style,
+    /// Category and group rows.
+    pub header: Style,
+    /// Characters that matched the search.
+    pub matched: Style,
+    /// The field being typed in.
+    pub focus: Style,
+    /// Keys in the help.
+    pub key: Style,
+    pub error: Style,
+    pub warning: Style,
+    /// Chips for tags, each tag gets one of these.
+    pub tags: Vec<Style>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::built_in("dark").unwrap()
+    }
+}
+
+impl Theme {
+    pub fn built_in(name: &str) -> Option<Theme> {
+        let dark = Theme {
+            border: border::ROUNDED,
+            highlight_symbol: ">>".to_string(),
+            text: Style::new(),
+            border_style: Style::new(),
+            selected: Style::new(),
+            header: Style::new().add_modifier(Modifier::BOLD),
+            matched: Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            focus: Style::new().fg(Color::Cyan),
+            key: Style::new().fg(Color::Cyan),
+            error: Style::new().fg(Color::Red),
+            warning: Style::new().fg(Color::Yellow),
+            tags: [
+                Color::Blue,
+                Color::Magenta,
+                Color::Green,
+                Color::Cyan,
+                Color::Red,
+                Color::Yellow,
+            ]
+            .map(|colour| Style::new().fg(Color::Black).bg(colour))
+            .to_vec(),
+        };
+        let theme = match name {
+            "dark" => dark,
+            "light" => Theme {
+                matched: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                focus: Style::new().fg(Color::Blue),
+                key: Style::new().fg(Color::Blue),
+                warning: Style::new().fg(Color::Magenta),
+                ..dark
+            },
+            "high-contrast" => Theme {
+                border: border::THICK,
+                highlight_symbol: "> ".to_string(),
+                text: Style::new().fg(Color::White).bg(Color::Black),
+                border_style: Style::new().fg(Color::White),
+                selected: Style::new()
+                    .fg(Color::Black)
+                    .bg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+                header: Style::new()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+                matched: Style::new()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                focus: Style::new()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                key: Style::new()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+                error: Style::new()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+                warning: Style::new()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                tags: vec![Style::new()
+                    .fg(Color::Black)
+                    .bg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD)],
+            },
+            "no-colour" => Theme {
+                border: border::PLAIN,
+                highlight_symbol: "> ".to_string(),
+                ..dark.without_colour()
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    /// The same theme with every colour taken out. Bold, reversed and the like are kept
+    /// so the selection and search matches still stand out. Anything that relied on
+    /// colour alone gets a modifier instead.
+    pub fn without_colour(self) -> Theme {
+        let plain = |style: Style| Style {
+            fg: None,
+            bg: None,
+            underline_color: None,
+            ..style
+        };
+        let marked = |style: Style, modifier: Modifier| {
+            let style = plain(style);
+            if style.add_modifier.is_empty() {
+                style.add_modifier(modifier)
+            } else {
+                style
+            }
+        };
+        Theme {
+            text: plain(self.text),
+            border_style: plain(self.border_style),
+            selected: marked(self.selected, Modifier::REVERSED),
+            header: plain(self.header),
+            matched: marked(self.matched, Modifier::UNDERLINED),
+            focus: marked(self.focus, Modifier::BOLD),
+            key: plain(self.key),
+            error: marked(self.error, Modifier::BOLD),
+            warning: marked(self.warning, Modifier::BOLD),
+            tags: self
+                .tags
+                .iter()
+                .map(|style| marked(*style, Modifier::REVERSED))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// A block with every border, as used for nearly everything on screen.
+    pub fn block(&self) -> Block<'static> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(self.border)
+            .border_style(self.border_style)
+    }
+
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        let style = match name {
+            "text" => &mut self.text,
+            "border_style" => &mut self.border_style,
+            "selected" => &mut self.selected,
+            "header" => &mut self.header,
+            "matched" => &mut self.matched,
+            "focus" => &mut self.focus,
+            "key" => &mut self.key,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            _ => return None,
+        };
+        Some(style)
+    }
+
+    /// Sets whatever `table` from a theme file has in it, returning what couldn't be.
+    fn apply(&mut self, table: &toml::Table) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (key, value) in table {
+            let result = match key.as_str() {
+                "extends" => Ok(()),
+                "border" => parse_border(value).map(|border| self.border = border),
+                "tags" => match value.as_array() {
+                    Some(values) => values
+                        .iter()
+                        .map(parse_style)
+                        .collect::<Result<_, _>>()
+                        .map(|styles| self.tags = styles),
+                    None => Err("should be a list of styles".to_string()),
+                },
+                "highlight_symbol" => match value.as_str() {
+                    Some(symbol) => {
+                        self.highlight_symbol = symbol.to_string();
+                        Ok(())
+                    }
+                    None => Err("should be a string".to_string()),
+                },
+                name => match self.style_mut(name) {
+                    Some(style) => parse_style(value).map(|parsed| *style = parsed),
+                    None => Err("isn't part of a theme".to_string()),
+                },
+            };
+            if let Err(e) = result {
+                errors.push(format!("`{}` {}", key, e));
+            }
+        }
+        errors
+    }
+}
+
+fn parse_border(value: &toml::Value) -> Result<border::Set, String> {
+    match value.as_str() {
+        Some("plain") => Ok(border::PLAIN),
+        Some("rounded") => Ok(border::ROUNDED),
+        Some("double") => Ok(border::DOUBLE),
+        Some("thick") => Ok(border::THICK),
+        Some("ascii") => Ok(ASCII),
+        _ => Err("should be one of plain, rounded, double, thick or ascii".to_string()),
+    }
+}
+
+/// A style is a colour name, or a table like `{ fg = "black", bg = "#a0c0ff", bold = true }`.
+/// Colours are names (`red`, `light-blue`), `#rrggbb` or a 256 colour index.
+fn parse_style(value: &toml::Value) -> Result<Style, String> {
+    let colour = |value: &toml::Value| {
+        value
+            .as_str()
+            .and_then(|name| Color::from_str(name).ok())
+            .ok_or_else(|| format!("has an unknown colour {}", value))
+    };
+    let table = match value {
+        toml::Value::String(_) => return Ok(Style::new().fg(colour(value)?)),
+        toml::Value::Table(table) => table,
+        _ => return Err("should be a colour or a table of fg, bg and modifiers".to_string()),
+    };
+    let mut style = Style::new();
+    for (key, value) in table {
+        let modifier = match key.as_str() {
+            "fg" => {
+                style = style.fg(colour(value)?);
+                continue;
+            }
+            "bg" => {
+                style = style.bg(colour(value)?);
+                continue;
+            }
+            "bold" => Modifier::BOLD,
+            "dim" => Modifier::DIM,
+            "italic" => Modifier::ITALIC,
+            "underlined" => Modifier::UNDERLINED,
+            "reversed" => Modifier::REVERSED,
+            _ => return Err(format!("has an unknown key `{}`", key)),
+        };
+        match value.as_bool() {
+            Some(true) => style = style.add_modifier(modifier),
+            Some(false) => style = style.remove_modifier(modifier),
+            None => return Err(format!("`{}` should be true or false", key)),
+        }
+    }
+    Ok(style)
+}
+
+/// Picks the theme called `name`, a built in one or `themes/<name>.toml` in the config
+/// directory. A theme file starts from the built in theme named in `extends`, dark if
+/// there's none, and changes what it lists. Colours are dropped when `NO_COLOR` is set.
+pub fn load(config_path: &Path, name: &str) -> (Theme, Vec<LoadError>) {
+    let (theme, errors) = match Theme::built_in(name) {
+        Some(theme) => (theme, Vec::new()),
+        None => load_file(&config_path.join(DIR).join(format!("{}.toml", name))),
+    };
+    // See https://no-color.org
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if no_color {
+        (theme.without_colour(), errors)
+    } else {
+        (theme, errors)
+    }
+}
+
+fn load_file(path: &Path) -> (Theme, Vec<LoadError>) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            let message = format!("Could not read the theme. {}", e);
+            return (Theme::default(), vec![LoadError::new(path, message)]);
+        }
+    };
+    let table = match toml::from_str::<toml::Table>(&contents) {
+        Ok(table) => table,
+        Err(e) => {
+            return (
+                Theme::default(),
+                vec![LoadError::from_toml(path, &contents, &e)],
+            )
+        }
+    };
+    let mut errors = Vec::new();
+    let extends = table.get("extends").and_then(|value| value.as_str());
+    let mut theme = match extends.map(|name| (name, Theme::built_in(name))) {
+        None => Theme::default(),
+        Some((_, Some(theme))) => theme,
+        Some((name, None)) => {
+            errors.push(format!(
+                "`extends` should be one of {}, not {}",
+                BUILT_IN.join(", "),
+                name
+            ));
+            Theme::default()
+        }
+    };
+    errors.extend(theme.apply(&table));
+    let errors = errors
+        .into_iter()
+        .map(|message| LoadError::new(path, message))
+        .collect();
+    (theme, errors)
+}
@@ -0,0 +1,315 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{self, Program},
+    settings::Settings,
+};
+
+/// What `${...}` in an entry can refer to. Built in are `${HOME}`, `${config_dir}`,
+/// `${env:NAME}`, `${entry.title}` and `${entry.id}`, everything else comes from `[vars]`
+/// in the settings. `$${` is a literal `${`. `${param.<name>}` is left as is until the
+/// entry is launched, see `fill_params`.
+pub struct Vars {
+    config_dir: PathBuf,
+    user: BTreeMap<String, String>,
+}
+
+impl Vars {
+    pub fn new(config_dir: &Path, user: &BTreeMap<String, String>) -> Vars {
+        Vars {
+            config_dir: config_dir.to_path_buf(),
+            user: user.clone(),
+        }
+    }
+
+    /// Fills in the variables in the fields of `program` that can have them: `command`,
+    /// `args`, `cwd`, `description`, the hooks and the values in `env`. The entry as written is kept
+    /// in `original` so it can be saved back unchanged.
+    pub fn expand(&self, program: &mut Program) -> Result<(), String> {
+        let cwd = program.cwd.as_ref().and_then(|cwd| cwd.to_str());
+        let templated = [&program.command, &program.description]
+            .into_iter()
+            .chain(&program.args)
+            .chain(program.env.values())
+            .chain(program.pre_launch.iter().flatten())
+            .chain(program.post_exit.iter().flatten())
+            .map(String::as_str)
+            .chain(cwd)
+            .any(|text| text.contains("${"));
+        if !templated {
+            return Ok(());
+        }
+
+        let original = program.clone();
+        let fill = |field: &str, text: &str| {
+            self.fill(text, &original)
+                .map_err(|e| format!("{} in `{}`", e, field))
+        };
+        program.command = fill("command", &original.command)?;
+        program.description = fill("description", &original.description)?;
+        for arg in &mut program.args {
+            *arg = fill("args", arg)?;
+        }
+        for value in program.env.values_mut() {
+            *value = fill("env", value)?;
+        }
+        for hook in program.pre_launch.iter_mut().flatten() {
+            *hook = fill("pre_launch", hook)?;
+        }
+        for hook in program.post_exit.iter_mut().flatten() {
+            *hook = fill("post_exit", hook)?;
+        }
+        if let Some(cwd) = original.cwd.as_ref().and_then(|cwd| cwd.to_str()) {
+            program.cwd = Some(PathBuf::from(fill("cwd", cwd)?));
+        }
+        program.original = Some(Box::new(original));
+        Ok(())
+    }
+
+    /// Fills in the variables in the hooks in the settings. They aren't part of an entry,
+    /// so `${entry.*}` and params can't be used there.
+    pub fn expand_settings(&self, settings: &mut Settings) -> Result<(), String> {
+        let hooks = [
+            ("pre_launch", &mut settings.pre_launch),
+            ("post_exit", &mut settings.post_exit),
+        ];
+        for (field, hooks) in hooks {
+            for hook in hooks {
+                *hook = fill(hook, |name| self.lookup(name, None))
+                    .map_err(|e| format!("{} in `{}`", e, field))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fill(&self, text: &str, program: &Program) -> Result<String, String> {
+        fill(text, |name| self.lookup(name, Some(program)))
+    }
+
+    fn lookup(&self, name: &str, program: Option<&Program>) -> Result<String, String> {
+        let value = match (name, program) {
+            ("HOME", _) => dirs::home_dir().map(|home| home.display().to_string()),
+            ("config_dir", _) => Some(self.config_dir.display().to_string()),
+            ("entry.title", Some(program)) => Some(program.title.clone()),
+            ("entry.id", Some(program)) => Some(program.id()),
+            _ => match (name.strip_prefix("env:"), name.strip_prefix("param.")) {
+                (Some(var), _) => env::var(var).ok(),
+                (_, Some(param))
+                    if program.is_some_and(|p| p.params.iter().any(|p| p.name == param)) =>
+                {
+                    Some(format!("${{{}}}", name))
+                }
+                _ => self.user.get(name).cloned(),
+            },
+        };
+        value.ok_or_else(|| format!("undefined variable `${{{}}}`", name))
+    }
+}
+
+/// Puts the answers to the entry's params in for `${param.<name>}` in `command`, `args`,
+/// `cwd`, the hooks and the values in `env`. Params without an answer get their default.
+/// In `command` and the hooks, which are run by the shell, answers are quoted so they
+/// stay one word and nothing in them gets run. Everywhere else they go in as is.
+pub fn fill_params(
+    program: &mut Program,
+    answers: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let mut values = BTreeMap::new();
+    for param in &program.params {
+        let value = answers
+            .get(&param.name)
+            .filter(|answer| !answer.is_empty())
+            .or(param.default.as_ref())
+            .ok_or_else(|| format!("{} needs an answer", param.label()))?;
+        if !param.choices.is_empty() && !param.choices.contains(value) {
+            return Err(format!(
+                "{} should be one of {}",
+                param.label(),
+                param.choices.join(", ")
+            ));
+        }
+        values.insert(format!("param.{}", param.name), value.clone());
+    }
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    // Anything else was filled in when the entry was loaded, so it's put back as it was.
+    let fill_with = |text: &str, shell: bool| {
+        fill(text, |name| {
+            Ok(match values.get(name) {
+                Some(value) if shell => config::quote(value),
+                Some(value) => value.clone(),
+                None => format!("${{{}}}", name),
+            })
+        })
+    };
+    program.command = fill_with(&program.command, true)?;
+    let hooks = program.pre_launch.iter_mut().chain(&mut program.post_exit);
+    for hook in hooks.flatten() {
+        *hook = fill_with(hook, true)?;
+    }
+    for arg in &mut program.args {
+        *arg = fill_with(arg, false)?;
+    }
+    for value in program.env.values_mut() {
+        *value = fill_with(value, false)?;
+    }
+    if let Some(cwd) = program.cwd.as_ref().and_then(|cwd| cwd.to_str()) {
+        program.cwd = Some(PathBuf::from(fill_with(cwd, false)?));
+    }
+    Ok(())
+}
+
+fn fill(text: &str, lookup: impl Fn(&str) -> Result<String, String>) -> Result<String, String> {
+    let mut filled = String::new();
+    let mut rest = text;
+    while let Some(at) = rest.find('$') {
+        filled.push_str(&rest[..at]);
+        rest = &rest[at..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            filled.push_str("${");
+            rest = after;
+            continue;
+        }
+        let Some(after) = rest.strip_prefix("${") else {
+            // A plain `$VAR` is left for the shell.
+            filled.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let Some(end) = after.find('}') else {
+            return Err("`${` without a closing `}`".to_string());
+        };
+        filled.push_str(&lookup(&after[..end])?);
+        rest = &after[end + 1..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Param;
+
+    fn vars() -> Vars {
+        let user = BTreeMap::from([("games".to_string(), "/mnt/games".to_string())]);
+        Vars::new(Path::new("/cfg"), &user)
+    }
+
+    fn program(command: &str) -> Program {
+        Program {
+            title: "Quake".to_string(),
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn param(name: &str, choices: &[&str], default: Option<&str>) -> Param {
+        Param {
+            name: name.to_string(),
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            default: default.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fills_in_variables() {
+        let mut quake = program("${games}/quake -c ${config_dir} ${entry.id}");
+        vars().expand(&mut quake).unwrap();
+        assert_eq!(quake.command, "/mnt/games/quake -c /cfg quake");
+        let original = quake.original.expect("the entry as written is kept");
+        assert_eq!(
+            original.command,
+            "${games}/quake -c ${config_dir} ${entry.id}"
+        );
+    }
+
+    #[test]
+    fn leaves_entries_without_variables_alone() {
+        let mut plain = program("echo $HOME");
+        vars().expand(&mut plain).unwrap();
+        assert_eq!(plain.command, "echo $HOME");
+        assert!(plain.original.is_none());
+    }
+
+    #[test]
+    fn double_dollar_is_a_literal() {
+        let lookup = |name: &str| Ok(format!("<{}>", name));
+        assert_eq!(fill("$${a} ${b}", lookup), Ok("${a} <b>".to_string()));
+        assert_eq!(fill("$$x $y $", lookup), Ok("$$x $y $".to_string()));
+    }
+
+    #[test]
+    fn unclosed_and_undefined_variables_are_errors() {
+        let lookup = |name: &str| Ok(name.to_string());
+        assert!(fill("echo ${games", lookup).is_err());
+
+        let mut quake = program("${nope}/quake");
+        let e = vars().expand(&mut quake).unwrap_err();
+        assert_eq!(e, "undefined variable `${nope}` in `command`");
+    }
+
+    #[test]
+    fn params_are_passed_through_until_launch() {
+        let mut ssh = program("ssh ${param.host}");
+        ssh.params = vec![param("host", &[], None)];
+        vars().expand(&mut ssh).unwrap();
+        assert_eq!(ssh.command, "ssh ${param.host}");
+
+        let mut undeclared = program("ssh ${param.other}");
+        undeclared.params = vec![param("host", &[], None)];
+        assert!(vars().expand(&mut undeclared).is_err());
+    }
+
+    #[test]
+    fn settings_hooks_cant_use_entry_variables() {
+        let mut settings = Settings {
+            pre_launch: vec!["mount ${games}".to_string()],
+            ..Default::default()
+        };
+        vars().expand_settings(&mut settings).unwrap();
+        assert_eq!(settings.pre_launch, ["mount /mnt/games"]);
+
+        settings.post_exit = vec!["echo ${entry.id}".to_string()];
+        assert!(vars().expand_settings(&mut settings).is_err());
+    }
+
+    #[test]
+    fn answers_are_quoted_for_the_shell_only() {
+        let mut ssh = program("ssh ${param.host}");
+        ssh.args = vec![];
+        ssh.env = BTreeMap::from([("HOST".to_string(), "${param.host}".to_string())]);
+        ssh.params = vec![param("host", &[], None)];
+        let answers = BTreeMap::from([("host".to_string(), "a b; rm x".to_string())]);
+        fill_params(&mut ssh, &answers).unwrap();
+        assert_eq!(ssh.command, "ssh 'a b; rm x'");
+        assert_eq!(ssh.env["HOST"], "a b; rm x");
+    }
+
+    #[test]
+    fn missing_answers_use_the_default() {
+        let mut ssh = program("ssh ${param.host}");
+        ssh.params = vec![param("host", &["nas", "pi"], Some("pi"))];
+        fill_params(&mut ssh, &BTreeMap::new()).unwrap();
+        assert_eq!(ssh.command, "ssh pi");
+
+        let mut ssh = program("ssh ${param.host}");
+        ssh.params = vec![param("host", &[], None)];
+        assert!(fill_params(&mut ssh, &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn answers_have_to_be_a_choice() {
+        let mut ssh = program("ssh ${param.host}");
+        ssh.params = vec![param("host", &["nas", "pi"], None)];
+        let answers = BTreeMap::from([("host".to_string(), "box".to_string())]);
+        assert!(fill_params(&mut ssh, &answers).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::config;
+
+#[derive(PartialEq)]
+pub enum Change {
+    /// The contents of this config file changed.
+    File(PathBuf),
+    /// Files or folders were added, removed or renamed, reload everything.
+    Tree,
+}
+
+/// Watches the config directory for changes made outside the launcher, like a dotfiles
+/// repo being pulled.
+pub struct ConfigWatcher {
+    // Stops watching when dropped.
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(root: &Path) -> notify::Result<ConfigWatcher> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+        Ok(ConfigWatcher {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Changes since the last call, without duplicates.
+    pub fn changes(&self) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for event in self.events.try_iter().flatten() {
+            let change = match event.kind {
+                EventKind::Create(_)
+                | EventKind::Remove(_)
+                | EventKind::Modify(ModifyKind::Name(_)) => {
+                    // Editors saving through a temporary file show up as creates and
+                    // renames of files we'd skip anyway.
+                    if !event
+                        .paths
+                        .iter()
+                        .any(|path| path.is_dir() || config::is_config_file(path))
+                    {
+                        continue;
+                    }
+                    Change::Tree
+                }
+                EventKind::Modify(_) => match event
+                    .paths
+                    .into_iter()
+                    .find(|path| config::is_config_file(path))
+                {
+                    Some(path) => Change::File(path),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            if !changes.contains(&change) {
+                changes.push(change);
+            }
+        }
+        changes
+    }
+}